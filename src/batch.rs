@@ -0,0 +1,33 @@
+use std::{sync::mpsc, thread};
+
+use crate::mdb_converter::{
+    decisions::{DecisionFile, MappingDecisions},
+    parser::parser,
+    report::Report,
+};
+use crate::tui::{log_list::LogLevel, AppEvent};
+
+/// Runs the converter without the TUI. Every answer comes from the mapping
+/// file, lines it can't answer end up in the returned report.
+pub fn run(cli: crate::cli::Args, mapping: &str) -> anyhow::Result<Report> {
+    let mut decisions = MappingDecisions::new(DecisionFile::load(mapping)?);
+
+    let (tx, rx) = mpsc::channel();
+    let handle = thread::spawn(move || parser(cli, tx, &mut decisions));
+
+    for event in rx {
+        if let AppEvent::Log(line, level) = event {
+            match level {
+                LogLevel::Trace => (),
+                LogLevel::Info => println!("{line}"),
+                LogLevel::Warn => eprintln!("warning: {line}"),
+                LogLevel::Error => eprintln!("error: {line}"),
+            }
+        }
+    }
+
+    let report = handle
+        .join()
+        .map_err(|_| anyhow::anyhow!("parser thread panicked"))?;
+    Ok(report)
+}
@@ -1,16 +1,36 @@
-use clap::{value_parser, Arg, ArgAction, Command};
+use clap::{builder::PossibleValuesParser, value_parser, Arg, ArgAction, ArgMatches, Command};
 
-pub fn cli() -> Option<Args> {
+use crate::inputs::{self, Filters, CPP_EXTENSIONS, MDB_EXTENSIONS};
+use crate::mdb_converter::{
+    encoding::MdbEncoding,
+    export::{ExportFormat, ReferenceMode, TranslateMode},
+    lint::LintFormat,
+    mdb_parser::DuplicatePolicy,
+    output::{Backup, OutputFormat, OutputOptions},
+};
+
+/// What the command line asks for.
+pub enum Mode {
+    Convert(Args),
+    Lint(LintArgs),
+    Export(ExportArgs),
+    Ts(TsArgs),
+}
+
+pub fn cli() -> Option<Mode> {
     let cli = Command::new("SRK-parser")
         .about("Parsing old FCP errors from .mdb files")
         .arg_required_else_help(true)
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
+        .subcommand(mdb_command())
         .args([
             Arg::new("mdb-files")
                 .short('M')
                 .required(true)
                 .long("path to .mdb files")
                 .value_parser(value_parser!(String))
-                .help("path to .mdb files")
+                .help("path to .mdb files, directories of them or globs")
                 .action(ArgAction::Set)
                 .num_args(1..),
             Arg::new("cpp-files")
@@ -18,13 +38,310 @@ pub fn cli() -> Option<Args> {
                 .required(true)
                 .long("path to .cpp files")
                 .value_parser(value_parser!(String))
-                .help("path to .cpp files")
+                .help("path to .cpp files, directories of them or globs")
                 .action(ArgAction::Set)
                 .num_args(1..),
+            Arg::new("include")
+                .long("include")
+                .value_name("GLOB")
+                .value_parser(value_parser!(String))
+                .help("only take files matching GLOB from -C directories")
+                .action(ArgAction::Append),
+            Arg::new("exclude")
+                .long("exclude")
+                .value_name("GLOB")
+                .value_parser(value_parser!(String))
+                .help("skip files matching GLOB in -C directories (.gitignore files are honoured too)")
+                .action(ArgAction::Append),
+            Arg::new("batch")
+                .short('B')
+                .long("batch")
+                .value_name("MAPPING")
+                .value_parser(value_parser!(String))
+                .help("run without the TUI, taking every answer from a mapping file")
+                .action(ArgAction::Set),
+            Arg::new("answers")
+                .short('A')
+                .long("answers")
+                .value_name("FILE")
+                .value_parser(value_parser!(String))
+                .help("replay answers from FILE and record every new one into it")
+                .conflicts_with("batch")
+                .action(ArgAction::Set),
+            Arg::new("mdb-encoding")
+                .long("mdb-encoding")
+                .value_parser(PossibleValuesParser::new(MdbEncoding::NAMES))
+                .default_value("auto")
+                .help("encoding of the .mdb files")
+                .action(ArgAction::Set),
+            Arg::new("duplicates")
+                .long("duplicates")
+                .value_parser(PossibleValuesParser::new(DuplicatePolicy::NAMES))
+                .default_value("first")
+                .help("which definition of a key defined twice in an .mdb file wins, or error")
+                .action(ArgAction::Set),
+            Arg::new("types")
+                .short('T')
+                .long("types")
+                .value_name("FILE")
+                .value_parser(value_parser!(String))
+                .help("known string-like variables and types for %s arguments")
+                .action(ArgAction::Set),
+            Arg::new("templates")
+                .long("templates")
+                .value_name("FILE")
+                .value_parser(value_parser!(String))
+                .help("replacement templates per mdb file, log function or directory")
+                .action(ArgAction::Set),
+            Arg::new("recognisers")
+                .long("recognisers")
+                .value_name("FILE")
+                .value_parser(value_parser!(String))
+                .help("log sinks to convert besides Qt streams, like std::cerr or printf")
+                .action(ArgAction::Set),
+            Arg::new("output-format")
+                .long("output-format")
+                .value_parser(PossibleValuesParser::new(OutputFormat::NAMES))
+                .default_value("out")
+                .help("write converted files, a diff per file or one combined patch")
+                .action(ArgAction::Set),
+            Arg::new("output-dir")
+                .short('o')
+                .long("output-dir")
+                .value_name("DIR")
+                .value_parser(value_parser!(String))
+                .default_value("output")
+                .help("directory for converted files, diffs and patches")
+                .action(ArgAction::Set),
+            Arg::new("in-place")
+                .short('i')
+                .long("in-place")
+                .help("overwrite the .cpp files instead of writing to the output directory")
+                .conflicts_with("output-format")
+                .action(ArgAction::SetTrue),
+            Arg::new("backup-suffix")
+                .long("backup-suffix")
+                .value_name("SUFFIX")
+                .value_parser(value_parser!(String))
+                .default_value(".bak")
+                .help("suffix of the backups made by --in-place")
+                .action(ArgAction::Set),
+            Arg::new("backup-dir")
+                .long("backup-dir")
+                .value_name("DIR")
+                .value_parser(value_parser!(String))
+                .help("put the backups made by --in-place into DIR, mirroring the source tree")
+                .conflicts_with("backup-suffix")
+                .action(ArgAction::Set),
+            Arg::new("no-backup")
+                .long("no-backup")
+                .help("don't back files up with --in-place")
+                .conflicts_with_all(["backup-suffix", "backup-dir"])
+                .action(ArgAction::SetTrue),
+            Arg::new("mirror")
+                .long("mirror")
+                .help("recreate the source directories under the output directory")
+                .action(ArgAction::SetTrue),
+            Arg::new("keep-names")
+                .long("keep-names")
+                .help("keep the original file names in the output directory")
+                .action(ArgAction::SetTrue),
+            Arg::new("logger-pattern")
+                .long("logger-pattern")
+                .value_name("REGEX")
+                .value_parser(value_parser!(String))
+                .help("regex for logger declarations, capturing the variable as `Name` and the mdb file as `Fcp`")
+                .action(ArgAction::Append),
+            Arg::new("no-infer")
+                .long("no-infer")
+                .help("always ask for logger names and mdb files instead of inferring them")
+                .action(ArgAction::SetTrue),
+            Arg::new("translate")
+                .long("translate")
+                .value_parser(PossibleValuesParser::new(TranslateMode::NAMES))
+                .help("make converted messages translatable with QObject::tr or QCoreApplication::translate")
+                .action(ArgAction::Set),
+            Arg::new("reference")
+                .long("reference")
+                .value_parser(PossibleValuesParser::new(ReferenceMode::NAMES))
+                .help("refer to messages as Mdb::ASM::Key or mdbMessage(FCP::ASM, \"Key\") and generate their definitions")
+                .action(ArgAction::Set),
+            Arg::new("escape-non-ascii")
+                .long("escape-non-ascii")
+                .help("write non-ASCII characters of mdb messages as \\x escapes")
+                .action(ArgAction::SetTrue),
         ]);
 
     let matches = cli.get_matches();
 
+    match matches.subcommand() {
+        Some(("mdb", mdb)) => match mdb.subcommand() {
+            Some(("lint", lint)) => lint_args(lint).map(Mode::Lint),
+            Some(("export", export)) => export_args(export).map(Mode::Export),
+            Some(("ts", ts)) => ts_args(ts).map(Mode::Ts),
+            _ => None,
+        },
+        _ => convert_args(&matches).map(Mode::Convert),
+    }
+}
+
+fn mdb_command() -> Command {
+    let files = Arg::new("files")
+        .value_name("MDB")
+        .required(true)
+        .value_parser(value_parser!(String))
+        .help(".mdb files, directories of them or globs")
+        .action(ArgAction::Set)
+        .num_args(1..);
+    let encoding = Arg::new("mdb-encoding")
+        .long("mdb-encoding")
+        .value_parser(PossibleValuesParser::new(MdbEncoding::NAMES))
+        .default_value("auto")
+        .help("encoding of the .mdb files")
+        .action(ArgAction::Set);
+    let duplicates = Arg::new("duplicates")
+        .long("duplicates")
+        .value_parser(PossibleValuesParser::new(DuplicatePolicy::NAMES))
+        .default_value("first")
+        .help("which definition of a key defined twice in an .mdb file wins, or error")
+        .action(ArgAction::Set);
+    let translate = Arg::new("translate")
+        .long("translate")
+        .value_parser(PossibleValuesParser::new(TranslateMode::NAMES))
+        .default_value("translate")
+        .help("context of QObject::tr or of QCoreApplication::translate per .mdb file")
+        .action(ArgAction::Set);
+
+    Command::new("mdb")
+        .about("Work with .mdb catalogs without touching any C++")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new("lint")
+                .about("Check .mdb files, exiting with 1 when anything is found")
+                .args([
+                    files.clone(),
+                    encoding.clone(),
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(PossibleValuesParser::new(LintFormat::NAMES))
+                        .default_value("text")
+                        .help("report as file:line:column lines or as JSON")
+                        .action(ArgAction::Set),
+                ]),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Write the messages of .mdb files as data or as a C++ header")
+                .args([
+                    files.clone(),
+                    encoding.clone(),
+                    duplicates.clone(),
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(PossibleValuesParser::new(ExportFormat::NAMES))
+                        .default_value("json")
+                        .help("json, yaml or csv data, or a C++ header with constants or enums")
+                        .action(ArgAction::Set),
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .value_parser(value_parser!(String))
+                        .help("write to FILE instead of stdout")
+                        .action(ArgAction::Set),
+                ]),
+        )
+        .subcommand(
+            Command::new("ts")
+                .about("Write a Qt Linguist .ts file per .mdb file")
+                .args([
+                    files,
+                    encoding,
+                    duplicates,
+                    translate,
+                    Arg::new("language")
+                        .long("language")
+                        .value_name("LANG")
+                        .value_parser(value_parser!(String))
+                        .help("target language of the .ts files, like ru_RU")
+                        .action(ArgAction::Set),
+                    Arg::new("output-dir")
+                        .short('o')
+                        .long("output-dir")
+                        .value_name("DIR")
+                        .value_parser(value_parser!(String))
+                        .default_value(".")
+                        .help("directory for the .ts files")
+                        .action(ArgAction::Set),
+                ]),
+        )
+}
+
+fn mdb_files(matches: &ArgMatches) -> Option<Vec<String>> {
+    let files: Vec<String> = matches.get_many("files")?.cloned().collect();
+    match inputs::expand(&files, &MDB_EXTENSIONS, &Filters::default()) {
+        Ok(files) => Some(files),
+        Err(e) => {
+            println!("{}", e);
+            None
+        }
+    }
+}
+
+fn lint_args(matches: &ArgMatches) -> Option<LintArgs> {
+    Some(LintArgs {
+        mdb_files: mdb_files(matches)?,
+        mdb_encoding: matches
+            .get_one::<String>("mdb-encoding")
+            .and_then(|e| MdbEncoding::from_str(e))
+            .unwrap_or_default(),
+        format: matches
+            .get_one::<String>("format")
+            .and_then(|f| LintFormat::from_str(f))
+            .unwrap_or_default(),
+    })
+}
+
+fn export_args(matches: &ArgMatches) -> Option<ExportArgs> {
+    Some(ExportArgs {
+        mdb_files: mdb_files(matches)?,
+        mdb_encoding: matches
+            .get_one::<String>("mdb-encoding")
+            .and_then(|e| MdbEncoding::from_str(e))
+            .unwrap_or_default(),
+        duplicates: matches
+            .get_one::<String>("duplicates")
+            .and_then(|d| DuplicatePolicy::from_str(d))
+            .unwrap_or_default(),
+        format: matches
+            .get_one::<String>("format")
+            .and_then(|f| ExportFormat::from_str(f))
+            .unwrap_or_default(),
+        output: matches.get_one("output").cloned(),
+    })
+}
+
+fn ts_args(matches: &ArgMatches) -> Option<TsArgs> {
+    Some(TsArgs {
+        mdb_files: mdb_files(matches)?,
+        mdb_encoding: matches
+            .get_one::<String>("mdb-encoding")
+            .and_then(|e| MdbEncoding::from_str(e))
+            .unwrap_or_default(),
+        duplicates: matches
+            .get_one::<String>("duplicates")
+            .and_then(|d| DuplicatePolicy::from_str(d))
+            .unwrap_or_default(),
+        translate: matches
+            .get_one::<String>("translate")
+            .and_then(|t| TranslateMode::from_str(t))?,
+        language: matches.get_one("language").cloned(),
+        output_dir: matches.get_one::<String>("output-dir")?.clone(),
+    })
+}
+
+fn convert_args(matches: &ArgMatches) -> Option<Args> {
     let mdb_files: Vec<String> = matches
         .get_many("mdb-files")
         .expect("Expected paths to .mdb files")
@@ -35,14 +352,84 @@ pub fn cli() -> Option<Args> {
         .expect("Expected paths to .cpp files")
         .cloned()
         .collect();
-
-    for file in &mdb_files {
-        if !std::path::Path::new(&file).exists() {
-            println!("Path does not exist: {}", &file);
+    let batch: Option<String> = matches.get_one("batch").cloned();
+    let answers: Option<String> = matches.get_one("answers").cloned();
+    let mdb_encoding = matches
+        .get_one::<String>("mdb-encoding")
+        .and_then(|e| MdbEncoding::from_str(e))
+        .unwrap_or_default();
+    let duplicates = matches
+        .get_one::<String>("duplicates")
+        .and_then(|d| DuplicatePolicy::from_str(d))
+        .unwrap_or_default();
+    let translate = matches
+        .get_one::<String>("translate")
+        .and_then(|t| TranslateMode::from_str(t));
+    let reference = matches
+        .get_one::<String>("reference")
+        .and_then(|r| ReferenceMode::from_str(r));
+    let escape_non_ascii = matches.get_flag("escape-non-ascii");
+    let types: Option<String> = matches.get_one("types").cloned();
+    let templates: Option<String> = matches.get_one("templates").cloned();
+    let recognisers: Option<String> = matches.get_one("recognisers").cloned();
+    let infer = !matches.get_flag("no-infer");
+    let logger_patterns: Vec<String> = matches
+        .get_many("logger-pattern")
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+    for pattern in &logger_patterns {
+        if let Err(e) = regex::Regex::new(pattern) {
+            println!("Invalid logger pattern {}: {}", pattern, e);
             return None;
         }
     }
-    for file in &cpp_files {
+    let backup = if matches.get_flag("no-backup") {
+        Backup::None
+    } else if let Some(dir) = matches.get_one::<String>("backup-dir") {
+        Backup::Dir(dir.clone())
+    } else {
+        Backup::Suffix(matches.get_one::<String>("backup-suffix").unwrap().clone())
+    };
+    let output = OutputOptions {
+        format: matches
+            .get_one::<String>("output-format")
+            .and_then(|f| OutputFormat::from_str(f))
+            .unwrap_or_default(),
+        dir: matches.get_one::<String>("output-dir").unwrap().clone(),
+        in_place: matches.get_flag("in-place"),
+        backup,
+        mirror: matches.get_flag("mirror"),
+        keep_names: matches.get_flag("keep-names"),
+    };
+
+    let filters = Filters {
+        include: matches
+            .get_many("include")
+            .unwrap_or_default()
+            .cloned()
+            .collect(),
+        exclude: matches
+            .get_many("exclude")
+            .unwrap_or_default()
+            .cloned()
+            .collect(),
+    };
+    // the filters pick C++ sources, every .mdb file of a directory is loaded
+    let expanded = inputs::expand(&mdb_files, &MDB_EXTENSIONS, &Filters::default())
+        .and_then(|mdb| Ok((mdb, inputs::expand(&cpp_files, &CPP_EXTENSIONS, &filters)?)));
+    let (mdb_files, cpp_files) = match expanded {
+        Ok(files) => files,
+        Err(e) => {
+            println!("{}", e);
+            return None;
+        }
+    };
+
+    for file in [&batch, &types, &templates, &recognisers]
+        .into_iter()
+        .flatten()
+    {
         if !std::path::Path::new(&file).exists() {
             println!("Path does not exist: {}", &file);
             return None;
@@ -52,11 +439,60 @@ pub fn cli() -> Option<Args> {
     Some(Args {
         mdb_files,
         cpp_files,
+        batch,
+        answers,
+        mdb_encoding,
+        duplicates,
+        escape_non_ascii,
+        translate,
+        reference,
+        types,
+        templates,
+        recognisers,
+        output,
+        infer,
+        logger_patterns,
     })
 }
 
+pub struct LintArgs {
+    pub mdb_files: Vec<String>,
+    pub mdb_encoding: MdbEncoding,
+    pub format: LintFormat,
+}
+
+pub struct ExportArgs {
+    pub mdb_files: Vec<String>,
+    pub mdb_encoding: MdbEncoding,
+    pub duplicates: DuplicatePolicy,
+    pub format: ExportFormat,
+    pub output: Option<String>,
+}
+
+pub struct TsArgs {
+    pub mdb_files: Vec<String>,
+    pub mdb_encoding: MdbEncoding,
+    pub duplicates: DuplicatePolicy,
+    pub translate: TranslateMode,
+    pub language: Option<String>,
+    pub output_dir: String,
+}
+
 #[derive(Default)]
 pub struct Args {
     pub mdb_files: Vec<String>,
     pub cpp_files: Vec<String>,
+    pub batch: Option<String>,
+    pub answers: Option<String>,
+    pub mdb_encoding: MdbEncoding,
+    pub duplicates: DuplicatePolicy,
+    pub escape_non_ascii: bool,
+    pub translate: Option<TranslateMode>,
+    pub reference: Option<ReferenceMode>,
+    pub types: Option<String>,
+    pub templates: Option<String>,
+    pub recognisers: Option<String>,
+    pub output: OutputOptions,
+    pub infer: bool,
+    pub logger_patterns: Vec<String>,
 }
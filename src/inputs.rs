@@ -0,0 +1,112 @@
+use globset::GlobBuilder;
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use std::path::{Path, PathBuf};
+
+pub const CPP_EXTENSIONS: [&str; 5] = ["cpp", "cc", "cxx", "h", "hpp"];
+pub const MDB_EXTENSIONS: [&str; 1] = ["mdb"];
+
+/// Include and exclude globs applied while walking directories.
+#[derive(Default, Clone)]
+pub struct Filters {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+/// Turns the paths given on the command line into a sorted list of files.
+/// Files are taken as is, directories and globs are walked for files with
+/// one of `extensions`, honouring `.gitignore` files and `filters`, and
+/// must hold at least one.
+pub fn expand(
+    paths: &[String],
+    extensions: &[&str],
+    filters: &Filters,
+) -> anyhow::Result<Vec<String>> {
+    let mut files = vec![];
+
+    for path in paths {
+        if Path::new(path).is_file() {
+            files.push(path.clone());
+        } else if Path::new(path).is_dir() {
+            let found = walk(Path::new(path), extensions, filters, None)?;
+            if found.is_empty() {
+                anyhow::bail!("No files found in {}", path);
+            }
+            files.extend(found);
+        } else if is_glob(path) {
+            let glob = GlobBuilder::new(path.trim_start_matches("./"))
+                .literal_separator(true)
+                .build()?
+                .compile_matcher();
+            let matched = walk(&glob_base(path), extensions, filters, Some(&glob))?;
+            if matched.is_empty() {
+                anyhow::bail!("No files match {}", path);
+            }
+            files.extend(matched);
+        } else {
+            anyhow::bail!("Path does not exist: {}", path);
+        }
+    }
+
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn walk(
+    dir: &Path,
+    extensions: &[&str],
+    filters: &Filters,
+    glob: Option<&globset::GlobMatcher>,
+) -> anyhow::Result<Vec<String>> {
+    let mut overrides = OverrideBuilder::new(dir);
+    for include in &filters.include {
+        overrides.add(include)?;
+    }
+    for exclude in &filters.exclude {
+        overrides.add(&format!("!{}", exclude))?;
+    }
+
+    let walker = WalkBuilder::new(dir)
+        .require_git(false)
+        .overrides(overrides.build()?)
+        .build();
+
+    let mut files = vec![];
+    for entry in walker {
+        let entry = entry?;
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let path = entry.path();
+        let path = path.strip_prefix("./").unwrap_or(path);
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        if !extensions.contains(&extension) {
+            continue;
+        }
+        if glob.is_some_and(|g| !g.is_match(path)) {
+            continue;
+        }
+        files.push(path.to_string_lossy().to_string());
+    }
+    Ok(files)
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '[', '{'])
+}
+
+/// Directory to start walking from: the components before the first one
+/// with a glob in it.
+fn glob_base(path: &str) -> PathBuf {
+    let mut base = PathBuf::new();
+    for component in path.split('/') {
+        if is_glob(component) {
+            break;
+        }
+        base.push(if component.is_empty() { "/" } else { component });
+    }
+    if base.as_os_str().is_empty() {
+        base.push(".");
+    }
+    base
+}
@@ -1,4 +1,7 @@
+pub mod batch;
 mod cli;
+pub mod inputs;
+pub mod mdb_cmd;
 pub mod mdb_converter;
 pub mod tui;
 
@@ -15,7 +18,18 @@ use ratatui::{
 use tui_logger::*;
 
 fn main() -> anyhow::Result<()> {
-    let cli = crate::cli::cli().unwrap();
+    let cli = match crate::cli::cli() {
+        Some(cli::Mode::Convert(cli)) => cli,
+        Some(cli::Mode::Lint(args)) => {
+            if !mdb_cmd::lint(args)? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(cli::Mode::Export(args)) => return mdb_cmd::export(args),
+        Some(cli::Mode::Ts(args)) => return mdb_cmd::ts(args),
+        None => std::process::exit(2),
+    };
     let mdb_files = cli.mdb_files.clone();
     let cpp_files = cli.cpp_files.clone();
 
@@ -31,6 +45,14 @@ fn main() -> anyhow::Result<()> {
             return Ok(());
         }
     }
+    if let Some(mapping) = cli.batch.clone() {
+        let report = batch::run(cli, &mapping)?;
+        if !report.is_clean() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     init_error_hooks()?;
     let terminal = init_terminal()?;
     init_logger(LevelFilter::Trace).unwrap();
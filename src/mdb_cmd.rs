@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::sync::mpsc;
+
+use crate::cli::{ExportArgs, LintArgs, TsArgs};
+use crate::mdb_converter::{
+    decisions::{DecisionFile, MappingDecisions},
+    encoding::MdbEncoding,
+    export::{export as export_catalogs, sorted_catalogs, to_ts},
+    lint::{lint as lint_files, LintFormat},
+    mdb_entry::Catalog,
+    mdb_parser::{get_loggers, DuplicatePolicy},
+    FCP,
+};
+use crate::tui::{log_list::LogLevel, AppEvent};
+
+/// Runs `mdb lint`, printing the report to stdout. Returns whether the files
+/// are clean.
+pub fn lint(args: LintArgs) -> anyhow::Result<bool> {
+    let issues = lint_files(&args.mdb_files, args.mdb_encoding);
+
+    match args.format {
+        LintFormat::Text => {
+            for issue in &issues {
+                println!("{}", issue);
+            }
+        }
+        LintFormat::Json => println!("{}", serde_json::to_string_pretty(&issues)?),
+    }
+    Ok(issues.is_empty())
+}
+
+/// Runs `mdb export`.
+pub fn export(args: ExportArgs) -> anyhow::Result<()> {
+    let loggers = load(&args.mdb_files, args.mdb_encoding, args.duplicates);
+
+    let exported = export_catalogs(&loggers, args.format)?;
+    match &args.output {
+        Some(path) => std::fs::write(path, exported)?,
+        None => print!("{}", exported),
+    }
+    Ok(())
+}
+
+/// Runs `mdb ts`, writing `<mdb name>.ts` files into the output directory.
+pub fn ts(args: TsArgs) -> anyhow::Result<()> {
+    let loggers = load(&args.mdb_files, args.mdb_encoding, args.duplicates);
+
+    std::fs::create_dir_all(&args.output_dir)?;
+    for (fcp, entries) in sorted_catalogs(&loggers) {
+        let ts = to_ts(fcp, &entries, args.translate, args.language.as_deref());
+        let path = std::path::Path::new(&args.output_dir).join(format!("{}.ts", fcp.to_str()));
+        std::fs::write(&path, ts)?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/// Loads mdb files without asking anything. Problems found while loading
+/// are printed to stderr and don't stop the command.
+fn load(
+    files: &[String],
+    encoding: MdbEncoding,
+    duplicates: DuplicatePolicy,
+) -> HashMap<FCP, Catalog> {
+    let (tx, rx) = mpsc::channel();
+    let mut decisions = MappingDecisions::new(DecisionFile::default());
+    let loggers = get_loggers(files, encoding, duplicates, tx, &mut decisions);
+
+    for event in rx {
+        if let AppEvent::Log(line, level) = event {
+            match level {
+                LogLevel::Trace | LogLevel::Info => (),
+                LogLevel::Warn => eprintln!("warning: {line}"),
+                LogLevel::Error => eprintln!("error: {line}"),
+            }
+        }
+    }
+    loggers
+}
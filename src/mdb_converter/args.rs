@@ -0,0 +1,300 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::mdb_entry::{ArgKind, MdbEntry};
+
+/// Compares the `<<` operands of a log line with the conversions of its mdb
+/// message. Returns a description of every problem found.
+pub fn check_args(entry: &MdbEntry, args: &[String]) -> Vec<String> {
+    let mut problems = vec![];
+
+    let expected = entry.arg_count();
+    if expected != args.len() {
+        problems.push(format!(
+            "{} expects {} argument(s), got {}",
+            entry.key,
+            expected,
+            args.len()
+        ));
+    }
+
+    let kinds = entry
+        .specs
+        .iter()
+        .flat_map(|spec| {
+            let stars = spec.arg_count().saturating_sub(1);
+            let kind = spec.kind();
+            std::iter::repeat_n(Some(ArgKind::Int), stars)
+                .chain(std::iter::once(kind))
+                .take(spec.arg_count())
+                .map(move |k| (spec, k))
+        })
+        .collect::<Vec<_>>();
+
+    for (i, ((spec, kind), arg)) in kinds.iter().zip(args).enumerate() {
+        let literal = literal_kind(arg);
+        let ok = match (kind, literal) {
+            (None, _) => {
+                problems.push(format!("unknown conversion {}", spec.text));
+                continue;
+            }
+            (_, None) => true,
+            (Some(ArgKind::Str), Some(lit)) => lit == ArgKind::Str,
+            (Some(ArgKind::Int | ArgKind::Char), Some(lit)) => {
+                lit == ArgKind::Int || lit == ArgKind::Char
+            }
+            (Some(ArgKind::Float), Some(lit)) => lit == ArgKind::Float || lit == ArgKind::Int,
+            (Some(_), Some(_)) => true,
+        };
+        if !ok {
+            problems.push(format!(
+                "argument {} ({}) doesn't fit {}",
+                i + 1,
+                arg,
+                spec.text
+            ));
+        }
+    }
+
+    problems
+}
+
+/// Type of `arg` when it is a plain literal, `None` for anything else.
+pub fn literal_kind(arg: &str) -> Option<ArgKind> {
+    let arg = arg.trim();
+    if arg.starts_with('"') {
+        Some(ArgKind::Str)
+    } else if arg.starts_with('\'') {
+        Some(ArgKind::Char)
+    } else if arg.parse::<i64>().is_ok() || arg.starts_with("0x") {
+        Some(ArgKind::Int)
+    } else if arg.trim_end_matches(['f', 'F']).parse::<f64>().is_ok() {
+        Some(ArgKind::Float)
+    } else {
+        None
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum StringKind {
+    CString,
+    QString,
+    StdString,
+    ByteArray,
+}
+
+impl StringKind {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        match s.trim_start_matches("const") {
+            "char*" | "char[]" => Some(Self::CString),
+            "QString" => Some(Self::QString),
+            "std::string" | "string" => Some(Self::StdString),
+            "QByteArray" => Some(Self::ByteArray),
+            _ => None,
+        }
+    }
+
+    /// Wraps `arg` so it can be passed to a `%s` conversion.
+    pub fn adapt(&self, arg: &str) -> String {
+        let simple = arg
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_.:->()[]".contains(c));
+        let operand = if simple {
+            arg.to_string()
+        } else {
+            format!("({arg})")
+        };
+        match self {
+            Self::CString => arg.to_string(),
+            Self::QString => format!("qPrintable({arg})"),
+            Self::StdString => format!("{operand}.c_str()"),
+            Self::ByteArray => format!("{operand}.constData()"),
+        }
+    }
+}
+
+/// Known string-like variables and types, from the `--types` file.
+///
+/// ```json
+/// {
+///     "variables": { "m_name": "QString" },
+///     "types": { "FcpString": "std::string" }
+/// }
+/// ```
+#[derive(Default, Clone, Deserialize)]
+pub struct ArgTypes {
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    #[serde(default)]
+    pub types: HashMap<String, String>,
+}
+
+impl ArgTypes {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let types = serde_json::from_str(&contents)?;
+        Ok(types)
+    }
+
+    fn kind_of_type(&self, type_name: &str) -> Option<StringKind> {
+        StringKind::from_str(type_name).or_else(|| {
+            self.types
+                .get(type_name.trim())
+                .and_then(|t| StringKind::from_str(t))
+        })
+    }
+
+    /// Adds the string-like variables declared in `buffer` to the table.
+    pub fn for_file(&self, buffer: &str) -> Self {
+        let mut types = self.clone();
+
+        let mut names = vec![
+            r"QString".to_string(),
+            r"QByteArray".to_string(),
+            r"(?:std::)?string".to_string(),
+        ];
+        names.extend(self.types.keys().map(|t| regex::escape(t)));
+        let re = Regex::new(&format!(
+            r"\b(?:const\s+)?(?P<Type>{})\s*[&*]?\s*(?P<Name>\w+)\s*[=;,)\[({{]",
+            names.join("|")
+        ))
+        .unwrap();
+        for cap in re.captures_iter(buffer) {
+            types
+                .variables
+                .entry(cap["Name"].to_string())
+                .or_insert_with(|| cap["Type"].to_string());
+        }
+
+        let re = Regex::new(r"\bchar\s*(?:\*\s*(?P<Ptr>\w+)|(?P<Arr>\w+)\s*\[)").unwrap();
+        for cap in re.captures_iter(buffer) {
+            let name = cap.name("Ptr").or(cap.name("Arr")).unwrap().as_str();
+            types
+                .variables
+                .entry(name.to_string())
+                .or_insert_with(|| "char*".to_string());
+        }
+
+        types
+    }
+
+    /// Guesses what kind of string `arg` is, `None` when it can't tell.
+    pub fn infer(&self, arg: &str) -> Option<StringKind> {
+        let arg = arg.trim();
+        if arg.starts_with('"')
+            || arg.starts_with("qPrintable(")
+            || arg.starts_with("qUtf8Printable(")
+        {
+            return Some(StringKind::CString);
+        }
+        if [".c_str()", ".data()", ".constData()"]
+            .iter()
+            .any(|s| arg.ends_with(s))
+        {
+            return Some(StringKind::CString);
+        }
+        if [".toUtf8()", ".toLocal8Bit()", ".toLatin1()"]
+            .iter()
+            .any(|s| arg.ends_with(s))
+        {
+            return Some(StringKind::ByteArray);
+        }
+        if arg.starts_with("QString(")
+            || arg.starts_with("QString::")
+            || arg.starts_with("tr(")
+            || arg.starts_with("QObject::tr(")
+            || arg.ends_with(".toString()")
+            || arg.contains(".arg(")
+        {
+            return Some(StringKind::QString);
+        }
+        if arg.starts_with("std::string(") || arg.starts_with("std::to_string(") {
+            return Some(StringKind::StdString);
+        }
+
+        self.variables.get(arg).and_then(|t| self.kind_of_type(t))
+    }
+}
+
+/// Wraps every string-like argument of a `%s` conversion. Returns the new
+/// arguments and those whose type couldn't be inferred.
+pub fn adapt_args(
+    entry: &MdbEntry,
+    args: &[String],
+    types: &ArgTypes,
+) -> (Vec<String>, Vec<String>) {
+    let mut adapted = vec![];
+    let mut unchecked = vec![];
+
+    let slots = entry
+        .specs
+        .iter()
+        .flat_map(|spec| {
+            let stars = spec.arg_count().saturating_sub(1);
+            std::iter::repeat_n(None, stars)
+                .chain(std::iter::once(spec.kind()))
+                .take(spec.arg_count())
+        })
+        .chain(std::iter::repeat(None));
+
+    for (arg, kind) in args.iter().zip(slots) {
+        if kind != Some(ArgKind::Str) {
+            adapted.push(arg.clone());
+            continue;
+        }
+        match types.infer(arg) {
+            Some(kind) => adapted.push(kind.adapt(arg)),
+            None => {
+                unchecked.push(arg.clone());
+                adapted.push(arg.clone());
+            }
+        }
+    }
+
+    (adapted, unchecked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(literal: &str, args: &[&str]) -> Vec<String> {
+        let entry = MdbEntry::new("Err1", literal, "t.mdb", 1);
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        check_args(&entry, &args)
+    }
+
+    #[test]
+    fn counts_arguments() {
+        assert!(check(r#""%s at %d""#, &["name", "line"]).is_empty());
+        assert_eq!(
+            check(r#""%s at %d""#, &["name"]),
+            ["Err1 expects 2 argument(s), got 1"]
+        );
+        assert_eq!(
+            check(r#""%*d""#, &["5"]),
+            ["Err1 expects 2 argument(s), got 1"]
+        );
+    }
+
+    #[test]
+    fn compares_literals_with_conversions() {
+        assert_eq!(
+            check(r#""%d %s %f""#, &["\"x\"", "3", "2"]),
+            [
+                "argument 1 (\"x\") doesn't fit %d",
+                "argument 2 (3) doesn't fit %s",
+            ]
+        );
+        assert!(check(r#""%d %c""#, &["'a'", "7"]).is_empty());
+        assert!(check(r#""%*s""#, &["10", "\"x\""]).is_empty());
+    }
+
+    #[test]
+    fn reports_unknown_conversions() {
+        assert_eq!(check(r#""%y""#, &["a"]), ["unknown conversion %y"]);
+    }
+}
@@ -0,0 +1,257 @@
+use regex::Regex;
+use std::collections::HashMap;
+
+use super::encoding::escape_non_ascii;
+use super::export::ReferenceMode;
+use super::lexer::{tokenize, Token, TokenKind};
+use super::mdb_entry::Catalog;
+use super::template::Templates;
+use super::FCP;
+
+/// Recognises statements an earlier run already rewrote, so converting a
+/// partially migrated file again leaves them alone.
+pub struct ConvertedForms {
+    /// Text the templates put right before the message.
+    markers: Vec<String>,
+    /// Every way a message can be written in a replacement, without
+    /// whitespace, with the entries it may stand for, sorted.
+    messages: HashMap<String, Vec<(FCP, String)>>,
+    reference_re: Regex,
+}
+
+impl ConvertedForms {
+    pub fn new(templates: &Templates, loggers: &HashMap<FCP, Catalog>) -> Self {
+        let mut messages: HashMap<String, Vec<(FCP, String)>> = HashMap::new();
+        for (fcp, catalog) in loggers {
+            for (key, entry) in catalog {
+                let forms = [
+                    entry.literal.clone(),
+                    escape_non_ascii(&entry.literal),
+                    ReferenceMode::Symbol.reference(*fcp, key),
+                    ReferenceMode::Lookup.reference(*fcp, key).replace(' ', ""),
+                ];
+                for form in forms {
+                    let entries = messages.entry(form).or_default();
+                    if !entries.contains(&(*fcp, key.clone())) {
+                        entries.push((*fcp, key.clone()));
+                    }
+                }
+            }
+        }
+        for entries in messages.values_mut() {
+            entries.sort();
+        }
+
+        Self {
+            markers: templates.message_markers(),
+            messages,
+            reference_re: Regex::new(r#"Mdb::\w+::\w+|mdbMessage\(FCP::\w+,"\w+"\)"#).unwrap(),
+        }
+    }
+
+    /// Entries whose message `statement` already logs the way the templates
+    /// write it, like `QString::asprintf("Error: %s", name)`. More than one
+    /// when several entries share the message.
+    pub fn find(&self, statement: &str) -> Option<&[(FCP, String)]> {
+        for marker in &self.markers {
+            let starts: Vec<usize> = if marker.is_empty() {
+                vec![0]
+            } else {
+                statement
+                    .match_indices(marker.as_str())
+                    .map(|(i, m)| i + m.len())
+                    .collect()
+            };
+            for start in starts {
+                if let Some(entries) = self.message_at(&statement[start..]) {
+                    return Some(entries);
+                }
+            }
+        }
+        None
+    }
+
+    /// Entries logged by the expression `src` starts with, looking inside
+    /// translation calls.
+    fn message_at(&self, src: &str) -> Option<&[(FCP, String)]> {
+        let tokens = tokenize(src);
+        let expression = first_expression(&tokens);
+
+        let literal = expression
+            .iter()
+            .filter(|t| t.kind == TokenKind::Str)
+            .find_map(|t| self.messages.get(t.text));
+        let text: String = expression.iter().map(|t| t.text).collect();
+        let reference = || {
+            self.reference_re
+                .find_iter(&text)
+                .find_map(|m| self.messages.get(m.as_str()))
+        };
+
+        literal.or_else(reference).map(|entries| entries.as_slice())
+    }
+}
+
+/// Tokens up to the first `,`, `;`, `<<` or unmatched closing bracket
+/// outside brackets.
+fn first_expression<'a>(tokens: &'a [Token<'a>]) -> &'a [Token<'a>] {
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate() {
+        if token.opens() {
+            depth += 1;
+        } else if token.closes() {
+            if depth == 0 {
+                return &tokens[..i];
+            }
+            depth -= 1;
+        } else if depth == 0 && [",", ";", "<<"].iter().any(|p| token.is(p)) {
+            return &tokens[..i];
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdb_converter::mdb_entry::MdbEntry;
+    use crate::mdb_converter::template::TemplateRule;
+
+    fn forms(templates: &Templates) -> ConvertedForms {
+        let entries = [
+            (FCP::ASM, "NoNets", r#""Info: Nets are not created yet!""#),
+            (
+                FCP::ASM,
+                "CantOpen",
+                r#""Error: couldn't open \"%s\" file!""#,
+            ),
+            (FCP::ASM, "Done", r#""Готово""#),
+            (FCP::SE, "NoNets", r#""Info: Nets are not created yet!""#),
+            (FCP::SE, "Other", r#""Info: Nets are not created yet!""#),
+            (FCP::SE, "Err8", r#""Pin %s not found""#),
+        ];
+        let mut loggers: HashMap<FCP, Catalog> = HashMap::new();
+        for (fcp, key, literal) in entries {
+            let entry = MdbEntry::new(key, literal, "t.mdb", 1);
+            loggers
+                .entry(fcp)
+                .or_default()
+                .insert(key.to_string(), entry);
+        }
+        ConvertedForms::new(templates, &loggers)
+    }
+
+    fn found(forms: &ConvertedForms, statement: &str) -> Option<Vec<String>> {
+        let entries = forms.find(statement)?;
+        Some(
+            entries
+                .iter()
+                .map(|(fcp, key)| format!("{}:{}", fcp.to_str(), key))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn finds_messages_the_default_template_wrote() {
+        let forms = forms(&Templates::default());
+        assert_eq!(
+            found(
+                &forms,
+                r#"qWarning() << QString::asprintf("Pin %s not found", pin) << ENDL;"#
+            ),
+            Some(vec!["fcpse:Err8".to_string()])
+        );
+        assert_eq!(
+            found(
+                &forms,
+                r#"qInfo() << QString::asprintf("\xd0\x93\xd0\xbe\xd1\x82\xd0\xbe\xd0\xb2\xd0\xbe") << ENDL;"#
+            ),
+            Some(vec!["fcpasm:Done".to_string()])
+        );
+        assert_eq!(found(&forms, r#"qWarning() << "Err8" << pin;"#), None);
+        assert_eq!(
+            found(
+                &forms,
+                r#"qWarning() << QString::asprintf("Pin %s", pin) << ENDL;"#
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn finds_messages_after_a_custom_template_marker() {
+        let templates = Templates {
+            default: crate::mdb_converter::template::DEFAULT_TEMPLATE.to_string(),
+            rules: vec![TemplateRule {
+                fcp: None,
+                severity: None,
+                dir: None,
+                template: "FCP_LOG({fcp}, {message}{comma_args});".to_string(),
+            }],
+        };
+        let forms = forms(&templates);
+        assert_eq!(
+            found(&forms, r#"FCP_LOG(fcpse, "Pin %s not found", pin);"#),
+            Some(vec!["fcpse:Err8".to_string()])
+        );
+        assert_eq!(found(&forms, r#"LOG("Pin %s not found", pin);"#), None);
+    }
+
+    #[test]
+    fn looks_inside_translation_calls() {
+        let forms = forms(&Templates::default());
+        let statement = r#"qCritical() << QString::asprintf(QObject::tr("Error: couldn't open \"%s\" file!", "CantOpen").toUtf8(), name) << ENDL;"#;
+        assert_eq!(
+            found(&forms, statement),
+            Some(vec!["fcpasm:CantOpen".to_string()])
+        );
+    }
+
+    #[test]
+    fn finds_catalog_references() {
+        let forms = forms(&Templates::default());
+        assert_eq!(
+            found(
+                &forms,
+                "qWarning() << QString::asprintf(Mdb::SE::Err8, pin) << ENDL;"
+            ),
+            Some(vec!["fcpse:Err8".to_string()])
+        );
+        assert_eq!(
+            found(
+                &forms,
+                r#"qWarning() << QString::asprintf(mdbMessage( FCP::SE , "Err8" ), pin) << ENDL;"#
+            ),
+            Some(vec!["fcpse:Err8".to_string()])
+        );
+        assert_eq!(
+            found(
+                &forms,
+                "qWarning() << QString::asprintf(Mdb::SE::Err9, pin) << ENDL;"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn keeps_every_entry_sharing_a_message() {
+        let forms = forms(&Templates::default());
+        let statement =
+            r#"qInfo() << QString::asprintf("Info: Nets are not created yet!") << ENDL;"#;
+        assert_eq!(
+            found(&forms, statement),
+            Some(vec![
+                "fcpse:NoNets".to_string(),
+                "fcpse:Other".to_string(),
+                "fcpasm:NoNets".to_string(),
+            ])
+        );
+        assert_eq!(
+            found(
+                &forms,
+                "qInfo() << QString::asprintf(Mdb::ASM::NoNets) << ENDL;"
+            ),
+            Some(vec!["fcpasm:NoNets".to_string()])
+        );
+    }
+}
@@ -0,0 +1,661 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::mpsc::{Receiver, Sender};
+
+use super::error::Error;
+use super::FCP;
+use crate::tui::{log_list::LogLevel, AppEvent};
+
+/// Source of every answer `parser` needs while converting a file: logger
+/// variable names and the mdb file to use for a log line.
+pub trait DecisionSource {
+    /// Variable name of the logger for `fcp`, used for comment-based auto-search.
+    fn logger_name(&mut self, fcp: FCP) -> Option<String>;
+
+    /// Variable name of the logger for `fcp` when it's known without asking.
+    fn known_logger_name(&self, _fcp: FCP) -> Option<String> {
+        None
+    }
+
+    /// Mdb file that `err` on `line_num` of `file_name` should be taken from.
+    fn select_fcp(
+        &mut self,
+        file_name: &str,
+        line_num: usize,
+        line: &str,
+        err: &str,
+        candidates: &[FCP],
+    ) -> Option<FCP>;
+
+    /// Whether the logger variables found in `file_name` should be used
+    /// instead of asking for each name.
+    fn confirm_loggers(&mut self, _file_name: &str, _discovered: &[(String, FCP)]) -> bool {
+        true
+    }
+
+    /// Whether to give up on `file_name` after `errors`, instead of going on
+    /// without the parts they affect.
+    fn skip_file(&mut self, _file_name: &str, _errors: &[Error]) -> bool {
+        false
+    }
+
+    /// Whether asking again can give a different answer.
+    fn interactive(&self) -> bool;
+}
+
+/// Asks the user through the TUI input field.
+pub struct TuiDecisions {
+    tx: Sender<AppEvent>,
+    rx: Receiver<AppEvent>,
+}
+
+impl TuiDecisions {
+    pub fn new(tx: Sender<AppEvent>, rx: Receiver<AppEvent>) -> Self {
+        Self { tx, rx }
+    }
+
+    fn wait_for_command(&self) -> String {
+        self.tx.send(AppEvent::WaitForInput).unwrap();
+        let mut name = String::new();
+        for event in &self.rx {
+            if let AppEvent::Command(n) = event {
+                name = n.trim().to_string();
+                self.tx
+                    .send(AppEvent::Log(name.clone(), LogLevel::Info))
+                    .unwrap();
+                break;
+            }
+        }
+        name
+    }
+}
+
+impl DecisionSource for TuiDecisions {
+    fn logger_name(&mut self, fcp: FCP) -> Option<String> {
+        let msg = format!(
+            "Specify variable name for logger {:?} (for auto-search based on comments)",
+            fcp
+        );
+        self.tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+        Some(self.wait_for_command())
+    }
+
+    fn select_fcp(
+        &mut self,
+        _file_name: &str,
+        line_num: usize,
+        line: &str,
+        _err: &str,
+        candidates: &[FCP],
+    ) -> Option<FCP> {
+        let mut base_str = format!(
+            "---------For line {line_num} select mdb file----------\n{}\n",
+            line.trim()
+        );
+        for (i, fcp) in candidates.iter().enumerate() {
+            base_str.push_str(&format!("{} - {}\n", i + 1, fcp.to_str()));
+        }
+
+        self.tx
+            .send(AppEvent::Log(base_str, LogLevel::Info))
+            .unwrap();
+        self.tx.send(AppEvent::JumpLine(line_num)).unwrap();
+
+        loop {
+            let name = self.wait_for_command();
+            match name.parse::<usize>() {
+                Ok(num) if num >= 1 && num <= candidates.len() => {
+                    return Some(candidates[num - 1]);
+                }
+                _ => {
+                    let msg = "wrong input! try again".to_string();
+                    self.tx.send(AppEvent::Log(msg, LogLevel::Error)).unwrap();
+                }
+            }
+        }
+    }
+
+    fn confirm_loggers(&mut self, file_name: &str, discovered: &[(String, FCP)]) -> bool {
+        let mut msg = format!("---------Found loggers in {file_name}----------\n");
+        for (name, fcp) in discovered {
+            msg.push_str(&format!("{} - {}\n", name, fcp.to_str()));
+        }
+        msg.push_str("Use them? (y/n)");
+        self.tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+
+        let answer = self.wait_for_command().to_lowercase();
+        answer == "y" || answer == "yes"
+    }
+
+    fn skip_file(&mut self, file_name: &str, errors: &[Error]) -> bool {
+        let msg = format!(
+            "{} error(s) in {}. (c)ontinue without the affected parts or (s)kip the file?",
+            errors.len(),
+            file_name
+        );
+        self.tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+
+        loop {
+            match self.wait_for_command().to_lowercase().as_str() {
+                "c" | "continue" => return false,
+                "s" | "skip" => return true,
+                _ => {
+                    let msg = "wrong input! try again".to_string();
+                    self.tx.send(AppEvent::Log(msg, LogLevel::Error)).unwrap();
+                }
+            }
+        }
+    }
+
+    fn interactive(&self) -> bool {
+        true
+    }
+}
+
+/// Mapping file for batch mode, also written by [`RecordingDecisions`].
+///
+/// ```json
+/// {
+///     "loggers": { "fcpasm": "asmLog" },
+///     "codes": { "Err1": "fcpasm" },
+///     "lines": [{ "file": "file.cpp", "line": 9, "code": "Err1", "fcp": "fcpse" }],
+///     "files": {
+///         "file.cpp": { "loggers": { "found": { "asmLog": "fcpasm" }, "used": true } },
+///         "other.cpp": { "errors": { "errors": ["other.cpp: ..."], "skip": true } }
+///     }
+/// }
+/// ```
+///
+/// Entries in `lines` take precedence over `codes`. A line entry with `text`
+/// only applies while the line still reads the same, wherever it moved to.
+/// Answers in `files` only apply while the file gives the same reasons to ask.
+#[derive(Default, Serialize, Deserialize)]
+pub struct DecisionFile {
+    #[serde(default)]
+    pub loggers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub codes: BTreeMap<String, String>,
+    #[serde(default)]
+    pub lines: Vec<LineDecision>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub files: BTreeMap<String, FileDecision>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LineDecision {
+    pub file: String,
+    pub line: usize,
+    pub code: String,
+    pub fcp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// Answers about a file as a whole.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct FileDecision {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loggers: Option<LoggersDecision>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub errors: Option<ErrorsDecision>,
+}
+
+/// Whether the logger variables `found` in a file were used.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LoggersDecision {
+    pub found: BTreeMap<String, String>,
+    pub used: bool,
+}
+
+/// Whether a file was skipped after `errors`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ErrorsDecision {
+    pub errors: Vec<String>,
+    pub skip: bool,
+}
+
+fn error_messages(errors: &[Error]) -> Vec<String> {
+    errors.iter().map(|e| e.to_string()).collect()
+}
+
+fn found_loggers(discovered: &[(String, FCP)]) -> BTreeMap<String, String> {
+    discovered
+        .iter()
+        .map(|(name, fcp)| (name.clone(), fcp.to_str()))
+        .collect()
+}
+
+impl DecisionFile {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let decisions = serde_json::from_str(&contents)?;
+        Ok(decisions)
+    }
+
+    pub fn load_or_default(path: &str) -> anyhow::Result<Self> {
+        if std::path::Path::new(path).exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    pub fn find_line(
+        &self,
+        file_name: &str,
+        line_num: usize,
+        err: &str,
+        line: &str,
+    ) -> Option<&LineDecision> {
+        let text = line.trim();
+        let same_text = |d: &&LineDecision| d.text.as_deref().is_none_or(|t| t == text);
+
+        self.lines
+            .iter()
+            .filter(|d| d.file == file_name && d.code == err)
+            .find(|d| d.line == line_num && same_text(d))
+            .or_else(|| {
+                self.lines.iter().find(|d| {
+                    d.file == file_name && d.code == err && d.text.as_deref() == Some(text)
+                })
+            })
+    }
+
+    /// Answer about using `discovered` in `file_name`, if the same loggers
+    /// were found there before.
+    pub fn find_loggers(&self, file_name: &str, discovered: &[(String, FCP)]) -> Option<bool> {
+        let decision = self.files.get(file_name)?.loggers.as_ref()?;
+        (decision.found == found_loggers(discovered)).then_some(decision.used)
+    }
+
+    pub fn record_loggers(&mut self, file_name: &str, discovered: &[(String, FCP)], used: bool) {
+        self.files.entry(file_name.to_string()).or_default().loggers = Some(LoggersDecision {
+            found: found_loggers(discovered),
+            used,
+        });
+    }
+
+    /// Answer about skipping `file_name`, if it had the same errors before.
+    pub fn find_skip(&self, file_name: &str, errors: &[Error]) -> Option<bool> {
+        let decision = self.files.get(file_name)?.errors.as_ref()?;
+        (decision.errors == error_messages(errors)).then_some(decision.skip)
+    }
+
+    pub fn record_skip(&mut self, file_name: &str, errors: &[Error], skip: bool) {
+        self.files.entry(file_name.to_string()).or_default().errors = Some(ErrorsDecision {
+            errors: error_messages(errors),
+            skip,
+        });
+    }
+
+    pub fn record_line(
+        &mut self,
+        file_name: &str,
+        line_num: usize,
+        err: &str,
+        line: &str,
+        fcp: FCP,
+    ) {
+        self.lines
+            .retain(|d| !(d.file == file_name && d.line == line_num && d.code == err));
+        self.lines.push(LineDecision {
+            file: file_name.to_string(),
+            line: line_num,
+            code: err.to_string(),
+            fcp: fcp.to_str(),
+            text: Some(line.trim().to_string()),
+        });
+    }
+}
+
+/// Takes every answer from a [`DecisionFile`], never asks.
+pub struct MappingDecisions {
+    file: DecisionFile,
+}
+
+impl MappingDecisions {
+    pub fn new(file: DecisionFile) -> Self {
+        Self { file }
+    }
+}
+
+impl DecisionSource for MappingDecisions {
+    fn logger_name(&mut self, fcp: FCP) -> Option<String> {
+        self.file.loggers.get(&fcp.to_str()).cloned()
+    }
+
+    fn known_logger_name(&self, fcp: FCP) -> Option<String> {
+        self.file.loggers.get(&fcp.to_str()).cloned()
+    }
+
+    fn select_fcp(
+        &mut self,
+        file_name: &str,
+        line_num: usize,
+        line: &str,
+        err: &str,
+        candidates: &[FCP],
+    ) -> Option<FCP> {
+        let fcp = self
+            .file
+            .find_line(file_name, line_num, err, line)
+            .map(|d| &d.fcp)
+            .or_else(|| self.file.codes.get(err))?;
+        FCP::from_str(fcp).filter(|fcp| candidates.contains(fcp))
+    }
+
+    fn confirm_loggers(&mut self, file_name: &str, discovered: &[(String, FCP)]) -> bool {
+        self.file
+            .find_loggers(file_name, discovered)
+            .unwrap_or(true)
+    }
+
+    fn skip_file(&mut self, file_name: &str, errors: &[Error]) -> bool {
+        self.file.find_skip(file_name, errors).unwrap_or(false)
+    }
+
+    fn interactive(&self) -> bool {
+        false
+    }
+}
+
+/// Replays answers from an answers file and asks `inner` only for the rest,
+/// saving every new answer right away.
+pub struct RecordingDecisions<D> {
+    inner: D,
+    path: String,
+    file: DecisionFile,
+    tx: Sender<AppEvent>,
+}
+
+impl<D: DecisionSource> RecordingDecisions<D> {
+    pub fn new(inner: D, path: &str, file: DecisionFile, tx: Sender<AppEvent>) -> Self {
+        Self {
+            inner,
+            path: path.to_string(),
+            file,
+            tx,
+        }
+    }
+
+    fn save(&self) {
+        if let Err(e) = self.file.save(&self.path) {
+            let msg = format!("Couldn't save answers to {}: {}", self.path, e);
+            self.tx.send(AppEvent::Log(msg, LogLevel::Error)).unwrap();
+        }
+    }
+}
+
+impl<D: DecisionSource> DecisionSource for RecordingDecisions<D> {
+    fn logger_name(&mut self, fcp: FCP) -> Option<String> {
+        if let Some(name) = self.file.loggers.get(&fcp.to_str()) {
+            let msg = format!("Replaying variable name {} for logger {:?}", name, fcp);
+            self.tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+            return Some(name.clone());
+        }
+
+        let name = self.inner.logger_name(fcp)?;
+        self.file.loggers.insert(fcp.to_str(), name.clone());
+        self.save();
+        Some(name)
+    }
+
+    fn known_logger_name(&self, fcp: FCP) -> Option<String> {
+        self.file
+            .loggers
+            .get(&fcp.to_str())
+            .cloned()
+            .or_else(|| self.inner.known_logger_name(fcp))
+    }
+
+    fn select_fcp(
+        &mut self,
+        file_name: &str,
+        line_num: usize,
+        line: &str,
+        err: &str,
+        candidates: &[FCP],
+    ) -> Option<FCP> {
+        let replayed = self
+            .file
+            .find_line(file_name, line_num, err, line)
+            .and_then(|d| FCP::from_str(&d.fcp))
+            .filter(|fcp| candidates.contains(fcp));
+        if let Some(fcp) = replayed {
+            let msg = format!(
+                "Replaying {} for {} on line {}",
+                fcp.to_str(),
+                err,
+                line_num
+            );
+            self.tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+            return Some(fcp);
+        }
+
+        let fcp = self
+            .inner
+            .select_fcp(file_name, line_num, line, err, candidates)?;
+        self.file.record_line(file_name, line_num, err, line, fcp);
+        self.save();
+        Some(fcp)
+    }
+
+    fn confirm_loggers(&mut self, file_name: &str, discovered: &[(String, FCP)]) -> bool {
+        if let Some(used) = self.file.find_loggers(file_name, discovered) {
+            let answer = if used { "using" } else { "not using" };
+            let msg = format!("Replaying {} the loggers found in {}", answer, file_name);
+            self.tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+            return used;
+        }
+
+        let used = self.inner.confirm_loggers(file_name, discovered);
+        self.file.record_loggers(file_name, discovered, used);
+        self.save();
+        used
+    }
+
+    fn skip_file(&mut self, file_name: &str, errors: &[Error]) -> bool {
+        if let Some(skip) = self.file.find_skip(file_name, errors) {
+            let answer = if skip { "skipping" } else { "continuing with" };
+            let msg = format!("Replaying {} {}", answer, file_name);
+            self.tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+            return skip;
+        }
+
+        let skip = self.inner.skip_file(file_name, errors);
+        self.file.record_skip(file_name, errors, skip);
+        self.save();
+        skip
+    }
+
+    fn interactive(&self) -> bool {
+        self.inner.interactive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    const LINE: &str = "qInfo() << \"Err1\";";
+
+    fn line(line: usize, fcp: &str, text: Option<&str>) -> LineDecision {
+        LineDecision {
+            file: "a.cpp".to_string(),
+            line,
+            code: "Err1".to_string(),
+            fcp: fcp.to_string(),
+            text: text.map(str::to_string),
+        }
+    }
+
+    fn found<'a>(file: &'a DecisionFile, line_num: usize, text: &str) -> Option<&'a str> {
+        file.find_line("a.cpp", line_num, "Err1", text)
+            .map(|d| d.fcp.as_str())
+    }
+
+    /// Answers with `fcp` and `used`, counting how often it's asked.
+    struct Fixed {
+        fcp: FCP,
+        used: bool,
+        asked: usize,
+    }
+
+    impl DecisionSource for Fixed {
+        fn logger_name(&mut self, _fcp: FCP) -> Option<String> {
+            self.asked += 1;
+            Some("log".to_string())
+        }
+
+        fn select_fcp(&mut self, _: &str, _: usize, _: &str, _: &str, _: &[FCP]) -> Option<FCP> {
+            self.asked += 1;
+            Some(self.fcp)
+        }
+
+        fn confirm_loggers(&mut self, _: &str, _: &[(String, FCP)]) -> bool {
+            self.asked += 1;
+            self.used
+        }
+
+        fn interactive(&self) -> bool {
+            true
+        }
+    }
+
+    fn recording(name: &str, fcp: FCP) -> (RecordingDecisions<Fixed>, String, Receiver<AppEvent>) {
+        let path = std::env::temp_dir()
+            .join(format!("answers-{}-{}.json", name, std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let _ = std::fs::remove_file(&path);
+        let inner = Fixed {
+            fcp,
+            used: false,
+            asked: 0,
+        };
+        let (tx, rx) = mpsc::channel();
+        let decisions = RecordingDecisions::new(inner, &path, DecisionFile::default(), tx);
+        (decisions, path, rx)
+    }
+
+    #[test]
+    fn finds_lines_by_number_or_by_text() {
+        let file = DecisionFile {
+            lines: vec![line(9, "fcpse", Some(LINE))],
+            ..Default::default()
+        };
+        assert_eq!(found(&file, 9, &format!("    {LINE}")), Some("fcpse"));
+        assert_eq!(found(&file, 12, LINE), Some("fcpse"));
+        assert_eq!(found(&file, 9, "qInfo() << \"Err1\" << a;"), None);
+        assert_eq!(
+            file.find_line("b.cpp", 9, "Err1", LINE).map(|d| &d.fcp),
+            None
+        );
+        assert_eq!(
+            file.find_line("a.cpp", 9, "Err2", LINE).map(|d| &d.fcp),
+            None
+        );
+    }
+
+    #[test]
+    fn lines_without_text_match_by_number_only() {
+        let file = DecisionFile {
+            lines: vec![line(9, "fcpse", None)],
+            ..Default::default()
+        };
+        assert_eq!(found(&file, 9, "anything"), Some("fcpse"));
+        assert_eq!(found(&file, 10, LINE), None);
+    }
+
+    #[test]
+    fn same_line_wins_over_a_moved_one() {
+        let file = DecisionFile {
+            lines: vec![line(3, "fcpasm", Some(LINE)), line(9, "fcpse", Some(LINE))],
+            ..Default::default()
+        };
+        assert_eq!(found(&file, 9, LINE), Some("fcpse"));
+        assert_eq!(found(&file, 5, LINE), Some("fcpasm"));
+    }
+
+    #[test]
+    fn recording_a_line_replaces_its_answer() {
+        let mut file = DecisionFile {
+            lines: vec![line(9, "fcpse", None), line(10, "fcpse", None)],
+            ..Default::default()
+        };
+        file.record_line("a.cpp", 9, "Err1", LINE, FCP::ASM);
+        assert_eq!(file.lines.len(), 2);
+        assert_eq!(found(&file, 9, LINE), Some("fcpasm"));
+        assert_eq!(found(&file, 10, LINE), Some("fcpse"));
+    }
+
+    #[test]
+    fn mapping_prefers_lines_over_codes() {
+        let file = DecisionFile {
+            codes: BTreeMap::from([("Err1".to_string(), "fcpasm".to_string())]),
+            lines: vec![line(9, "fcpse", None)],
+            ..Default::default()
+        };
+        let mut decisions = MappingDecisions::new(file);
+        let both = [FCP::ASM, FCP::SE];
+        assert_eq!(
+            decisions.select_fcp("a.cpp", 9, LINE, "Err1", &both),
+            Some(FCP::SE)
+        );
+        assert_eq!(
+            decisions.select_fcp("a.cpp", 4, LINE, "Err1", &both),
+            Some(FCP::ASM)
+        );
+        assert_eq!(
+            decisions.select_fcp("a.cpp", 4, LINE, "Err1", &[FCP::SE]),
+            None
+        );
+    }
+
+    #[test]
+    fn recording_asks_once_and_saves_the_answer() {
+        let (mut decisions, path, _rx) = recording("lines", FCP::SE);
+        let candidates = [FCP::ASM, FCP::SE];
+        for line_num in [9, 9, 14] {
+            let fcp = decisions.select_fcp("a.cpp", line_num, LINE, "Err1", &candidates);
+            assert_eq!(fcp, Some(FCP::SE));
+        }
+        assert_eq!(decisions.inner.asked, 1);
+        assert_eq!(decisions.logger_name(FCP::SE).as_deref(), Some("log"));
+        assert_eq!(decisions.logger_name(FCP::SE).as_deref(), Some("log"));
+        assert_eq!(decisions.inner.asked, 2);
+
+        let saved = DecisionFile::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(found(&saved, 9, LINE), Some("fcpse"));
+        assert_eq!(saved.loggers.get("fcpse").map(String::as_str), Some("log"));
+    }
+
+    #[test]
+    fn recording_replays_loggers_while_the_same_are_found() {
+        let (mut decisions, path, _rx) = recording("loggers", FCP::SE);
+        let asm = [("asmLog".to_string(), FCP::ASM)];
+        let both = [
+            ("asmLog".to_string(), FCP::ASM),
+            ("seLog".to_string(), FCP::SE),
+        ];
+        assert!(!decisions.confirm_loggers("a.cpp", &asm));
+        assert!(!decisions.confirm_loggers("a.cpp", &asm));
+        assert_eq!(decisions.inner.asked, 1);
+        decisions.confirm_loggers("a.cpp", &both);
+        decisions.confirm_loggers("b.cpp", &both);
+        assert_eq!(decisions.inner.asked, 3);
+
+        let saved = DecisionFile::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved.find_loggers("a.cpp", &both), Some(false));
+        assert_eq!(saved.find_loggers("a.cpp", &asm), None);
+    }
+}
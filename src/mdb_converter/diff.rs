@@ -0,0 +1,311 @@
+use std::path::{Component, Path, PathBuf};
+
+use super::error::{Error, Result};
+
+const CONTEXT: usize = 3;
+
+/// Original lines `first_line..=last_line` (1-based) replaced by `new_text`.
+#[derive(Clone, Debug)]
+pub struct Change {
+    pub first_line: usize,
+    pub last_line: usize,
+    pub new_text: String,
+}
+
+/// Replaces byte ranges of `original`, in order, and keeps the changed
+/// lines as [`Change`]s. Replacements on the same line end up in one change
+/// holding the fully rewritten lines.
+pub struct Rewrite<'a> {
+    original: &'a str,
+    text: String,
+    /// End of the last replaced range in `original`.
+    last: usize,
+    /// Line `last` is on.
+    line: usize,
+    changes: Vec<Change>,
+    /// Offset in `text` of the first line of the last change.
+    change_start: usize,
+}
+
+impl<'a> Rewrite<'a> {
+    pub fn new(original: &'a str) -> Self {
+        Self {
+            original,
+            text: String::new(),
+            last: 0,
+            line: 1,
+            changes: vec![],
+            change_start: 0,
+        }
+    }
+
+    /// Replaces `original[start..end]`, which must come after the ranges
+    /// replaced so far, with `new_text`. Returns the change holding it.
+    pub fn replace(&mut self, start: usize, end: usize, new_text: &str) -> &Change {
+        let skipped = &self.original[self.last..start];
+        let first_line = self.line + skipped.matches('\n').count();
+        let last_line = first_line + self.original[start..end].matches('\n').count();
+        self.text.push_str(skipped);
+
+        let merged = self
+            .changes
+            .last()
+            .filter(|c| c.last_line >= first_line)
+            .map(|c| c.first_line);
+        if merged.is_none() {
+            let line_start = self.original[..start].rfind('\n').map_or(0, |n| n + 1);
+            self.change_start = self.text.len() - (start - line_start);
+        }
+        self.text.push_str(new_text);
+        self.last = end;
+        self.line = last_line;
+
+        let line_end = self.original[end..]
+            .find('\n')
+            .map_or(self.original.len(), |n| end + n);
+        let after = &self.original[end..line_end];
+        let change = Change {
+            first_line: merged.unwrap_or(first_line),
+            last_line,
+            new_text: format!(
+                "{}{}",
+                &self.text[self.change_start..],
+                after.strip_suffix('\r').unwrap_or(after)
+            ),
+        };
+        if merged.is_some() {
+            self.changes.pop();
+        }
+        self.changes.push(change);
+        self.changes.last().unwrap()
+    }
+
+    /// The rewritten text and its changes.
+    pub fn finish(mut self) -> (String, Vec<Change>) {
+        self.text.push_str(&self.original[self.last..]);
+        (self.text, self.changes)
+    }
+}
+
+/// `path` relative to `cwd`, with `/` separators, the way `git apply` wants
+/// paths in a diff run from `cwd`.
+pub fn patch_path(path: &str, cwd: &Path) -> Result<String> {
+    let mut absolute = PathBuf::new();
+    for component in cwd.join(path).components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            c => absolute.push(c),
+        }
+    }
+
+    let relative = absolute
+        .strip_prefix(cwd)
+        .ok()
+        .filter(|r| r.components().next().is_some())
+        .ok_or_else(|| Error::PatchPath {
+            path: path.to_string(),
+        })?;
+    let parts: Vec<_> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    Ok(parts.join("/"))
+}
+
+/// Unified diff of `changes` applied to `original`, with `a/` and `b/`
+/// prefixed paths so it applies with `git apply`. `path` comes from
+/// [`patch_path`].
+pub fn unified_diff(path: &str, original: &str, changes: &[Change]) -> String {
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let lines: Vec<&str> = original.split_inclusive('\n').collect();
+    let mut res = format!("--- a/{path}\n+++ b/{path}\n");
+
+    let mut groups: Vec<&[Change]> = vec![];
+    let mut start = 0;
+    for i in 1..=changes.len() {
+        let split = i == changes.len()
+            || changes[i].first_line > changes[i - 1].last_line + 2 * CONTEXT + 1;
+        if split {
+            groups.push(&changes[start..i]);
+            start = i;
+        }
+    }
+
+    let mut delta: isize = 0;
+    for group in groups {
+        let old_start = group[0].first_line.saturating_sub(CONTEXT).max(1);
+        let old_end = (group[group.len() - 1].last_line + CONTEXT).min(lines.len());
+
+        let mut body = String::new();
+        let mut new_count = 0;
+        let mut line = old_start;
+        for change in group {
+            while line < change.first_line {
+                push_line(&mut body, ' ', lines[line - 1]);
+                new_count += 1;
+                line += 1;
+            }
+            for old in &lines[change.first_line - 1..change.last_line] {
+                push_line(&mut body, '-', old);
+            }
+            let ending = line_ending(lines[change.last_line - 1]);
+            for new in format!("{}{}", change.new_text, ending).split_inclusive('\n') {
+                push_line(&mut body, '+', new);
+                new_count += 1;
+            }
+            line = change.last_line + 1;
+        }
+        while line <= old_end {
+            push_line(&mut body, ' ', lines[line - 1]);
+            new_count += 1;
+            line += 1;
+        }
+
+        let old_count = old_end - old_start + 1;
+        let new_start = old_start as isize + delta;
+        res.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n{}",
+            old_start, old_count, new_start, new_count, body
+        ));
+        delta += new_count as isize - old_count as isize;
+    }
+
+    res
+}
+
+fn line_ending(line: &str) -> &str {
+    if line.ends_with("\r\n") {
+        "\r\n"
+    } else if line.ends_with('\n') {
+        "\n"
+    } else {
+        ""
+    }
+}
+
+fn push_line(body: &mut String, marker: char, line: &str) {
+    body.push(marker);
+    body.push_str(line);
+    if !line.ends_with('\n') {
+        body.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(first_line: usize, last_line: usize, new_text: &str) -> Change {
+        Change {
+            first_line,
+            last_line,
+            new_text: new_text.to_string(),
+        }
+    }
+
+    #[test]
+    fn hunk_has_context_around_a_change() {
+        let original = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let diff = unified_diff("a.cpp", original, &[change(5, 5, "five")]);
+        assert_eq!(
+            diff,
+            "--- a/a.cpp\n+++ b/a.cpp\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn later_hunks_start_after_added_lines() {
+        let original: String = (1..=20).map(|n| format!("{n}\n")).collect();
+        let changes = [change(2, 2, "two\nmore"), change(15, 16, "fifteen")];
+        let diff = unified_diff("a.cpp", &original, &changes);
+        let headers: Vec<&str> = diff.lines().filter(|l| l.starts_with("@@")).collect();
+        assert_eq!(headers, ["@@ -1,5 +1,6 @@", "@@ -12,8 +13,7 @@"]);
+    }
+
+    #[test]
+    fn close_changes_share_a_hunk() {
+        let original: String = (1..=12).map(|n| format!("{n}\n")).collect();
+        let diff = unified_diff("a.cpp", &original, &[change(3, 3, "x"), change(9, 9, "y")]);
+        assert_eq!(diff.matches("@@ -").count(), 1);
+    }
+
+    #[test]
+    fn missing_final_newline_is_marked() {
+        let diff = unified_diff("a.cpp", "a\nb", &[change(2, 2, "c")]);
+        assert!(
+            diff.ends_with("-b\n\\ No newline at end of file\n+c\n\\ No newline at end of file\n")
+        );
+    }
+
+    #[test]
+    fn keeps_crlf_line_endings() {
+        let diff = unified_diff("a.cpp", "a\r\nb\r\n", &[change(1, 1, "c")]);
+        assert!(diff.contains("-a\r\n+c\r\n b\r\n"));
+    }
+
+    #[test]
+    fn rewrite_merges_statements_on_one_line() {
+        let original = "int a;\nf(1); f(2); // x\nint b;\n";
+        let mut rewrite = Rewrite::new(original);
+        rewrite.replace(7, 12, "g(1);");
+        let change = rewrite.replace(13, 18, "g(2);").clone();
+        assert_eq!((change.first_line, change.last_line), (2, 2));
+        assert_eq!(change.new_text, "g(1); g(2); // x");
+
+        let (text, changes) = rewrite.finish();
+        assert_eq!(text, "int a;\ng(1); g(2); // x\nint b;\n");
+        assert_eq!(changes.len(), 1);
+        let diff = unified_diff("a.cpp", original, &changes);
+        assert_eq!(diff.matches("\n-").count(), 1);
+    }
+
+    #[test]
+    fn rewrite_merges_a_statement_starting_on_the_last_line_of_another() {
+        let original = "f(1,\n  2); f(3);\nend\n";
+        let mut rewrite = Rewrite::new(original);
+        rewrite.replace(0, 10, "g(1,\n  2);");
+        rewrite.replace(11, 16, "g(3);");
+        let (text, changes) = rewrite.finish();
+        assert_eq!(text, "g(1,\n  2); g(3);\nend\n");
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].first_line, changes[0].last_line), (1, 2));
+        assert_eq!(changes[0].new_text, "g(1,\n  2); g(3);");
+    }
+
+    #[test]
+    fn rewrite_keeps_separate_lines_apart() {
+        let original = "f(1);\r\nx;\r\nf(2);\r\n";
+        let mut rewrite = Rewrite::new(original);
+        rewrite.replace(0, 5, "g(1);");
+        rewrite.replace(11, 16, "g(2);");
+        let (text, changes) = rewrite.finish();
+        assert_eq!(text, "g(1);\r\nx;\r\ng(2);\r\n");
+        let lines: Vec<(usize, &str)> = changes
+            .iter()
+            .map(|c| (c.first_line, c.new_text.as_str()))
+            .collect();
+        assert_eq!(lines, [(1, "g(1);"), (3, "g(2);")]);
+    }
+
+    #[test]
+    fn patch_paths_are_relative_to_the_current_directory() {
+        let cwd = Path::new("/work/repo");
+        let path = |p: &str| patch_path(p, cwd).map_err(|e| e.to_string());
+        assert_eq!(path("./src/a.cpp").as_deref(), Ok("src/a.cpp"));
+        assert_eq!(path("src/../lib/./b.cpp").as_deref(), Ok("lib/b.cpp"));
+        assert_eq!(path("/work/repo/src/a.cpp").as_deref(), Ok("src/a.cpp"));
+        assert_eq!(path("../repo/a.cpp").as_deref(), Ok("a.cpp"));
+        assert_eq!(
+            path("/tmp/rv/t.cpp"),
+            Err("/tmp/rv/t.cpp is outside the current directory, no diff can name it".into())
+        );
+        assert!(path("../other/a.cpp").is_err());
+        assert!(path("/work/repository/a.cpp").is_err());
+    }
+}
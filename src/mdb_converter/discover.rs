@@ -0,0 +1,93 @@
+use regex::Regex;
+
+use super::FCP;
+
+/// Declarations of logger objects bound to an mdb file, such as
+/// `MdbLogger asmLog("fcpasm")` or `auto asmLog = MdbLogger("fcpasm")`.
+/// Patterns capture the variable as `Name` and the mdb file as `Fcp`.
+pub const DEFAULT_LOGGER_PATTERNS: [&str; 2] = [
+    r#"\b\w*Logger\s*[*&]?\s*(?P<Name>\w+)\s*(?:[({=]\s*)+"(?P<Fcp>\w+)""#,
+    r#"\b(?P<Name>\w+)\s*=\s*(?:new\s+)?\w*Logger\s*[({]\s*"(?P<Fcp>\w+)""#,
+];
+
+/// Logger variables declared in `buffer` for one of `fcps`, in order of
+/// appearance.
+pub fn discover_loggers(buffer: &str, patterns: &[Regex], fcps: &[FCP]) -> Vec<(String, FCP)> {
+    let mut found: Vec<(String, FCP)> = vec![];
+
+    for re in patterns {
+        for cap in re.captures_iter(buffer) {
+            let (Some(name), Some(fcp)) = (cap.name("Name"), cap.name("Fcp")) else {
+                continue;
+            };
+            let fcp = fcp.as_str().to_lowercase();
+            let fcp = FCP::from_str(&fcp).or_else(|| FCP::from_str(&format!("fcp{fcp}")));
+            let Some(fcp) = fcp.filter(|f| fcps.contains(f)) else {
+                continue;
+            };
+            if !found.iter().any(|(n, _)| n == name.as_str()) {
+                found.push((name.as_str().to_string(), fcp));
+            }
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn discover(buffer: &str) -> Vec<(String, FCP)> {
+        let patterns: Vec<Regex> = DEFAULT_LOGGER_PATTERNS
+            .iter()
+            .map(|p| Regex::new(p).unwrap())
+            .collect();
+        discover_loggers(buffer, &patterns, &[FCP::SE, FCP::ASM])
+    }
+
+    fn one(buffer: &str) -> Option<(String, FCP)> {
+        match discover(buffer).as_slice() {
+            [found] => Some(found.clone()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn finds_declarations() {
+        let asm = Some(("asmLog".to_string(), FCP::ASM));
+        assert_eq!(one("MdbLogger asmLog(\"fcpasm\");"), asm);
+        assert_eq!(
+            one("static MdbLogger* asmLog = new MdbLogger(\"fcpasm\");"),
+            asm
+        );
+        assert_eq!(one("const MdbLogger &asmLog{\"fcpasm\"};"), asm);
+    }
+
+    #[test]
+    fn finds_initialisations_with_short_names() {
+        let asm = Some(("x".to_string(), FCP::ASM));
+        assert_eq!(one("auto x = MdbLogger(\"asm\");"), asm);
+        assert_eq!(one("x = new MdbLogger(\"ASM\");"), asm);
+    }
+
+    #[test]
+    fn keeps_the_first_binding_of_a_name() {
+        let buffer =
+            "MdbLogger seLog(\"fcpse\");\nMdbLogger asmLog(\"asm\");\nseLog = MdbLogger(\"asm\");";
+        assert_eq!(
+            discover(buffer),
+            [
+                ("seLog".to_string(), FCP::SE),
+                ("asmLog".to_string(), FCP::ASM)
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_unloaded_and_unknown_mdb_files() {
+        assert!(discover("MdbLogger drcLog(\"fcpdrc\");").is_empty());
+        assert!(discover("MdbLogger log(\"network\");").is_empty());
+        assert!(discover("report(asmLog, \"fcpasm\");").is_empty());
+    }
+}
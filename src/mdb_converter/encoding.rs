@@ -0,0 +1,172 @@
+use encoding_rs::{IBM866, WINDOWS_1251};
+use std::borrow::Cow;
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub enum MdbEncoding {
+    #[default]
+    Auto,
+    Utf8,
+    Cp1251,
+    Cp866,
+    Latin1,
+}
+
+impl MdbEncoding {
+    pub const NAMES: [&'static str; 5] = ["auto", "utf-8", "cp1251", "cp866", "latin1"];
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "utf-8" | "utf8" => Some(Self::Utf8),
+            "cp1251" | "windows-1251" => Some(Self::Cp1251),
+            "cp866" | "ibm866" => Some(Self::Cp866),
+            "latin1" | "iso-8859-1" => Some(Self::Latin1),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(&self) -> String {
+        let s = match self {
+            Self::Auto => "auto",
+            Self::Utf8 => "utf-8",
+            Self::Cp1251 => "cp1251",
+            Self::Cp866 => "cp866",
+            Self::Latin1 => "latin1",
+        };
+        s.to_string()
+    }
+
+    /// Decodes `bytes`, returning the text and the encoding actually used.
+    /// Undecodable bytes become U+FFFD instead of failing the whole file.
+    pub fn decode(self, bytes: &[u8]) -> (String, Self) {
+        let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+        let encoding = match self {
+            Self::Auto => detect(bytes),
+            encoding => encoding,
+        };
+
+        let text = match encoding {
+            Self::Utf8 | Self::Auto => String::from_utf8_lossy(bytes).into_owned(),
+            Self::Cp1251 => WINDOWS_1251
+                .decode_without_bom_handling(bytes)
+                .0
+                .into_owned(),
+            Self::Cp866 => IBM866.decode_without_bom_handling(bytes).0.into_owned(),
+            Self::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+        };
+        (text, encoding)
+    }
+}
+
+/// Valid UTF-8 stays UTF-8, otherwise picks the Cyrillic code page that
+/// decodes more of the bytes to Russian letters, cp1251 on a tie.
+fn detect(bytes: &[u8]) -> MdbEncoding {
+    if std::str::from_utf8(bytes).is_ok() {
+        return MdbEncoding::Utf8;
+    }
+
+    // both code pages have the whole alphabet, just at different bytes, so
+    // text in the wrong one decodes partly to box drawing and punctuation
+    let letters = |text: Cow<str>| {
+        text.chars()
+            .filter(|c| matches!(c, 'А'..='я' | 'Ё' | 'ё'))
+            .count()
+    };
+    let cp1251 = letters(WINDOWS_1251.decode_without_bom_handling(bytes).0);
+    let cp866 = letters(IBM866.decode_without_bom_handling(bytes).0);
+
+    if cp866 > cp1251 {
+        MdbEncoding::Cp866
+    } else {
+        MdbEncoding::Cp1251
+    }
+}
+
+/// Rewrites non-ASCII characters of a C++ string literal as `\x` escapes of
+/// their UTF-8 bytes, so the output doesn't depend on the compiler's source
+/// charset.
+pub fn escape_non_ascii(literal: &str) -> String {
+    let mut res = String::new();
+    let mut escaped = false;
+    for c in literal.chars() {
+        if c.is_ascii() {
+            // a hex digit right after `\xNN` would extend the escape
+            if escaped && c.is_ascii_hexdigit() {
+                res.push_str("\" \"");
+            }
+            res.push(c);
+            escaped = false;
+        } else {
+            let mut buf = [0; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                res.push_str(&format!("\\x{:02x}", b));
+            }
+            escaped = true;
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_utf8_and_drops_its_bom() {
+        let (text, encoding) = MdbEncoding::Auto.decode("\u{FEFF}Err1 \"ошибка\"".as_bytes());
+        assert_eq!(text, "Err1 \"ошибка\"");
+        assert_eq!(encoding, MdbEncoding::Utf8);
+    }
+
+    #[test]
+    fn reads_a_single_cp1251_letter() {
+        // the `и` on the DelPgRingTrace line of the old fcpasm.mdb
+        let bytes = b"DelPgRingTrace \"Trace \"%s\" \xE8 \"%s\"\"";
+        let (text, encoding) = MdbEncoding::Auto.decode(bytes);
+        assert_eq!(encoding, MdbEncoding::Cp1251);
+        assert_eq!(text, "DelPgRingTrace \"Trace \"%s\" и \"%s\"\"");
+    }
+
+    #[test]
+    fn tells_lowercase_cp1251_from_cp866() {
+        let message = "Err1 \"ошибка: «файл» не найден %s\"";
+        let cp1251 = WINDOWS_1251.encode(message).0;
+        assert_eq!(
+            MdbEncoding::Auto.decode(&cp1251),
+            (message.to_string(), MdbEncoding::Cp1251)
+        );
+
+        let message = "Err1 \"ошибка: файл не найден %s\"";
+        let cp866 = IBM866.encode(message).0;
+        assert!(std::str::from_utf8(&cp866).is_err());
+        assert_eq!(
+            MdbEncoding::Auto.decode(&cp866),
+            (message.to_string(), MdbEncoding::Cp866)
+        );
+    }
+
+    #[test]
+    fn tells_uppercase_cp1251_from_cp866() {
+        let message = "Err1 \"ОШИБКА: ФАЙЛ НЕ НАЙДЕН\"";
+        for (bytes, encoding) in [
+            (WINDOWS_1251.encode(message).0, MdbEncoding::Cp1251),
+            (IBM866.encode(message).0, MdbEncoding::Cp866),
+        ] {
+            assert_eq!(
+                MdbEncoding::Auto.decode(&bytes),
+                (message.to_string(), encoding)
+            );
+        }
+    }
+
+    #[test]
+    fn given_encodings_are_used_as_is() {
+        assert_eq!(
+            MdbEncoding::Latin1.decode(b"caf\xE9"),
+            ("café".to_string(), MdbEncoding::Latin1)
+        );
+        assert_eq!(MdbEncoding::Cp866.decode(b"\xE8").0, "ш");
+        assert_eq!(MdbEncoding::Utf8.decode(b"a\xE8").0, "a\u{FFFD}");
+    }
+}
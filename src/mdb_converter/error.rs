@@ -0,0 +1,97 @@
+use std::fmt;
+
+use super::FCP;
+
+/// Problems the converter reports and recovers from instead of panicking.
+#[derive(Debug)]
+pub enum Error {
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    /// The file name doesn't name a known mdb file, like `fcpasm.mdb`.
+    NotAnMdb { path: String },
+    /// A line of an mdb file that doesn't follow the mdb grammar.
+    MdbSyntax {
+        path: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A key defined a second time on `line` of the same mdb file, with the
+    /// line whose definition is used, if any.
+    DuplicateKey {
+        path: String,
+        key: String,
+        first_line: usize,
+        line: usize,
+        kept: Option<usize>,
+    },
+    /// A message with unescaped quotes inside, read up to the last quote.
+    StrayQuotes {
+        path: String,
+        line: usize,
+        key: String,
+    },
+    /// A replacement template that can't be filled in.
+    Template { message: String },
+    /// A log line was assigned to an mdb file that wasn't loaded.
+    MissingMdb { fcp: FCP },
+    /// A file a patch can't name, being outside the current directory.
+    PatchPath { path: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read { path, source } => write!(f, "couldn't read {}: {}", path, source),
+            Self::NotAnMdb { path } => write!(f, "{} is not named after a known mdb file", path),
+            Self::MdbSyntax {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path, line, column, message),
+            Self::DuplicateKey {
+                path,
+                key,
+                first_line,
+                line,
+                kept,
+            } => {
+                write!(
+                    f,
+                    "{}:{}: {} is already defined on line {}",
+                    path, line, key, first_line
+                )?;
+                match kept {
+                    Some(kept) => write!(f, ", keeping line {}", kept),
+                    None => Ok(()),
+                }
+            }
+            Self::StrayQuotes { path, line, key } => write!(
+                f,
+                "{}:{}: unescaped quotes in the message of {}, kept as part of it",
+                path, line, key
+            ),
+            Self::Template { message } => write!(f, "{}", message),
+            Self::MissingMdb { fcp } => write!(f, "{} is not loaded", fcp.to_str()),
+            Self::PatchPath { path } => write!(
+                f,
+                "{} is outside the current directory, no diff can name it",
+                path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Read { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,612 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::mdb_entry::{ArgKind, Catalog, MdbEntry};
+use super::FCP;
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Yaml,
+    Csv,
+    /// C++ header with a `constexpr const char*` per key.
+    Header,
+    /// C++ header with an `enum class` of keys per mdb file.
+    Enum,
+}
+
+impl ExportFormat {
+    pub const NAMES: [&'static str; 5] = ["json", "yaml", "csv", "header", "enum"];
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "json" => Some(Self::Json),
+            "yaml" => Some(Self::Yaml),
+            "csv" => Some(Self::Csv),
+            "header" => Some(Self::Header),
+            "enum" => Some(Self::Enum),
+            _ => None,
+        }
+    }
+}
+
+/// Qt call making a message translatable. Either way the key is passed as
+/// the disambiguation, which Qt Linguist shows as the comment.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum TranslateMode {
+    /// `QObject::tr(message, key)`, in the `QObject` context.
+    Tr,
+    /// `QCoreApplication::translate("fcpasm", message, key)`, in a context
+    /// per mdb file.
+    Translate,
+}
+
+impl TranslateMode {
+    pub const NAMES: [&'static str; 2] = ["tr", "translate"];
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "tr" => Some(Self::Tr),
+            "translate" => Some(Self::Translate),
+            _ => None,
+        }
+    }
+
+    /// Translation context the messages of `fcp` end up in.
+    pub fn context(self, fcp: FCP) -> String {
+        match self {
+            Self::Tr => "QObject".to_string(),
+            Self::Translate => fcp.to_str(),
+        }
+    }
+
+    /// Call translating `literal`, the message of `key` in `fcp`.
+    pub fn wrap(self, fcp: FCP, literal: &str, key: &str) -> String {
+        match self {
+            Self::Tr => format!("QObject::tr({}, \"{}\")", literal, key),
+            Self::Translate => format!(
+                "QCoreApplication::translate(\"{}\", {}, \"{}\")",
+                fcp.to_str(),
+                literal,
+                key
+            ),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ExportEntry<'a> {
+    fcp: String,
+    key: &'a str,
+    message: &'a str,
+    args: Vec<ExportArg<'a>>,
+}
+
+#[derive(Serialize)]
+struct ExportArg<'a> {
+    spec: &'a str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+/// Catalogs sorted by mdb file, entries in file order.
+pub fn sorted_catalogs(loggers: &HashMap<FCP, Catalog>) -> Vec<(FCP, Vec<&MdbEntry>)> {
+    let mut catalogs: Vec<(FCP, Vec<&MdbEntry>)> = loggers
+        .iter()
+        .map(|(fcp, catalog)| {
+            let mut entries: Vec<&MdbEntry> = catalog.values().collect();
+            entries.sort_by_key(|e| e.line);
+            (*fcp, entries)
+        })
+        .collect();
+    catalogs.sort_by_key(|(fcp, _)| *fcp);
+    catalogs
+}
+
+pub fn export(loggers: &HashMap<FCP, Catalog>, format: ExportFormat) -> anyhow::Result<String> {
+    let catalogs = sorted_catalogs(loggers);
+    let entries: Vec<ExportEntry> = catalogs
+        .iter()
+        .flat_map(|(fcp, entries)| entries.iter().map(|e| export_entry(*fcp, e)))
+        .collect();
+
+    let res = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&entries)? + "\n",
+        ExportFormat::Yaml => to_yaml(&entries)?,
+        ExportFormat::Csv => to_csv(&entries),
+        ExportFormat::Header => to_header(&catalogs),
+        ExportFormat::Enum => to_enum_header(&catalogs),
+    };
+    Ok(res)
+}
+
+fn export_entry(fcp: FCP, entry: &MdbEntry) -> ExportEntry<'_> {
+    let args = entry
+        .specs
+        .iter()
+        .filter(|s| s.conversion != '%')
+        .map(|s| ExportArg {
+            spec: &s.text,
+            kind: kind_name(s.kind()),
+        })
+        .collect();
+    ExportEntry {
+        fcp: fcp.to_str(),
+        key: &entry.key,
+        message: &entry.message,
+        args,
+    }
+}
+
+fn kind_name(kind: Option<ArgKind>) -> &'static str {
+    match kind {
+        Some(ArgKind::Int) => "int",
+        Some(ArgKind::Float) => "float",
+        Some(ArgKind::Char) => "char",
+        Some(ArgKind::Str) => "string",
+        Some(ArgKind::Pointer) => "pointer",
+        Some(ArgKind::Written) => "written",
+        Some(ArgKind::Percent) => "percent",
+        None => "invalid",
+    }
+}
+
+/// YAML sequence of entries. Scalars are written as JSON strings, which YAML
+/// reads as double-quoted strings.
+fn to_yaml(entries: &[ExportEntry]) -> anyhow::Result<String> {
+    let q = |s: &str| serde_json::to_string(s);
+
+    let mut res = String::new();
+    for e in entries {
+        writeln!(res, "- fcp: {}", q(&e.fcp)?)?;
+        writeln!(res, "  key: {}", q(e.key)?)?;
+        writeln!(res, "  message: {}", q(e.message)?)?;
+        if e.args.is_empty() {
+            writeln!(res, "  args: []")?;
+            continue;
+        }
+        writeln!(res, "  args:")?;
+        for arg in &e.args {
+            writeln!(res, "    - spec: {}", q(arg.spec)?)?;
+            writeln!(res, "      type: {}", arg.kind)?;
+        }
+    }
+    Ok(res)
+}
+
+fn to_csv(entries: &[ExportEntry]) -> String {
+    let field = |s: &str| {
+        if s.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    };
+
+    let mut res = "fcp,key,message,args\n".to_string();
+    for e in entries {
+        let args: Vec<&str> = e.args.iter().map(|a| a.spec).collect();
+        res.push_str(&format!(
+            "{},{},{},{}\n",
+            field(&e.fcp),
+            field(e.key),
+            field(e.message),
+            field(&args.join(" "))
+        ));
+    }
+    res
+}
+
+/// Keywords and alternative operator tokens of C++20, which can't name a
+/// constant or an enumerator.
+const CPP_KEYWORDS: [&str; 92] = [
+    "alignas",
+    "alignof",
+    "and",
+    "and_eq",
+    "asm",
+    "auto",
+    "bitand",
+    "bitor",
+    "bool",
+    "break",
+    "case",
+    "catch",
+    "char",
+    "char16_t",
+    "char32_t",
+    "char8_t",
+    "class",
+    "co_await",
+    "co_return",
+    "co_yield",
+    "compl",
+    "concept",
+    "const",
+    "const_cast",
+    "consteval",
+    "constexpr",
+    "constinit",
+    "continue",
+    "decltype",
+    "default",
+    "delete",
+    "do",
+    "double",
+    "dynamic_cast",
+    "else",
+    "enum",
+    "explicit",
+    "export",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "friend",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "mutable",
+    "namespace",
+    "new",
+    "noexcept",
+    "not",
+    "not_eq",
+    "nullptr",
+    "operator",
+    "or",
+    "or_eq",
+    "private",
+    "protected",
+    "public",
+    "register",
+    "reinterpret_cast",
+    "requires",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "static_assert",
+    "static_cast",
+    "struct",
+    "switch",
+    "template",
+    "this",
+    "thread_local",
+    "throw",
+    "true",
+    "try",
+    "typedef",
+    "typeid",
+    "typename",
+    "union",
+    "unsigned",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "wchar_t",
+    "while",
+    "xor",
+    "xor_eq",
+];
+
+/// C++ identifier for `key`, which is already a word but may be a keyword.
+pub fn cpp_name(key: &str) -> String {
+    if CPP_KEYWORDS.contains(&key) {
+        format!("{}_", key)
+    } else {
+        key.to_string()
+    }
+}
+
+/// Namespace or enum name of an mdb file, like `ASM` for fcpasm.mdb.
+pub fn cpp_fcp_name(fcp: FCP) -> String {
+    format!("{:?}", fcp)
+}
+
+const HEADER_NOTE: &str = "// Generated from .mdb files by SRK-parser, don't edit.\n";
+
+fn to_header(catalogs: &[(FCP, Vec<&MdbEntry>)]) -> String {
+    let mut res = format!("{}#pragma once\n\nnamespace Mdb {{\n", HEADER_NOTE);
+    for (fcp, entries) in catalogs {
+        res.push_str(&format!("\nnamespace {} {{\n", cpp_fcp_name(*fcp)));
+        for e in entries {
+            res.push_str(&format!(
+                "constexpr const char* {} = {};\n",
+                cpp_name(&e.key),
+                e.literal
+            ));
+        }
+        res.push_str(&format!("}} // namespace {}\n", cpp_fcp_name(*fcp)));
+    }
+    res.push_str("\n} // namespace Mdb\n");
+    res
+}
+
+fn to_enum_header(catalogs: &[(FCP, Vec<&MdbEntry>)]) -> String {
+    let mut res = format!("{}#pragma once\n\nnamespace Mdb {{\n", HEADER_NOTE);
+    for (fcp, entries) in catalogs {
+        let name = cpp_fcp_name(*fcp);
+
+        res.push_str(&format!("\nenum class {} {{\n", name));
+        for e in entries {
+            res.push_str(&format!("    {},\n", cpp_name(&e.key)));
+        }
+        res.push_str("};\n");
+
+        res.push_str(&format!(
+            "\ninline const char* message({} code)\n{{\n    switch (code) {{\n",
+            name
+        ));
+        for e in entries {
+            res.push_str(&format!(
+                "    case {}::{}: return {};\n",
+                name,
+                cpp_name(&e.key),
+                e.literal
+            ));
+        }
+        res.push_str("    }\n    return \"\";\n}\n");
+    }
+    res.push_str("\n} // namespace Mdb\n");
+    res
+}
+
+/// Qt Linguist file with the messages of one mdb file, left unfinished for
+/// translators.
+pub fn to_ts(
+    fcp: FCP,
+    entries: &[&MdbEntry],
+    translate: TranslateMode,
+    language: Option<&str>,
+) -> String {
+    let mut res = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE TS>\n".to_string();
+    match language {
+        Some(language) => res.push_str(&format!(
+            "<TS version=\"2.1\" language=\"{}\">\n",
+            xml_escape(language)
+        )),
+        None => res.push_str("<TS version=\"2.1\">\n"),
+    }
+
+    res.push_str(&format!(
+        "<context>\n    <name>{}</name>\n",
+        xml_escape(&translate.context(fcp))
+    ));
+    for e in entries {
+        res.push_str(&format!(
+            "    <message>\n        <source>{}</source>\n        <comment>{}</comment>\n        <translation type=\"unfinished\"></translation>\n    </message>\n",
+            xml_escape(&e.message),
+            xml_escape(&e.key)
+        ));
+    }
+    res.push_str("</context>\n</TS>\n");
+    res
+}
+
+/// Escapes text for XML. Control characters XML can't hold are written the
+/// way Qt Linguist writes them.
+fn xml_escape(s: &str) -> String {
+    let mut res = String::new();
+    for c in s.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&apos;"),
+            '\n' | '\t' | '\r' => res.push(c),
+            c if (c as u32) < 0x20 => res.push_str(&format!("<byte value=\"x{:x}\"/>", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// How converted statements refer to the catalog instead of inlining the
+/// message.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum ReferenceMode {
+    /// `Mdb::ASM::Key`, a constant per key.
+    Symbol,
+    /// `mdbMessage(FCP::ASM, "Key")`, a lookup by key.
+    Lookup,
+}
+
+/// Base name of the generated companion header and source.
+pub const REFERENCE_FILE: &str = "mdb_messages";
+
+impl ReferenceMode {
+    pub const NAMES: [&'static str; 2] = ["symbol", "lookup"];
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "symbol" => Some(Self::Symbol),
+            "lookup" => Some(Self::Lookup),
+            _ => None,
+        }
+    }
+
+    /// Expression standing for the message of `key` in `fcp`.
+    pub fn reference(self, fcp: FCP, key: &str) -> String {
+        match self {
+            Self::Symbol => format!("Mdb::{}::{}", cpp_fcp_name(fcp), cpp_name(key)),
+            Self::Lookup => format!("mdbMessage(FCP::{}, \"{}\")", cpp_fcp_name(fcp), key),
+        }
+    }
+
+    /// Header declaring what [`Self::reference`] refers to.
+    pub fn header(self, catalogs: &[(FCP, Vec<&MdbEntry>)]) -> String {
+        let mut res = format!("{}#pragma once\n", HEADER_NOTE);
+        match self {
+            Self::Symbol => {
+                res.push_str("\nnamespace Mdb {\n");
+                for (fcp, entries) in catalogs {
+                    res.push_str(&format!("\nnamespace {} {{\n", cpp_fcp_name(*fcp)));
+                    for e in entries {
+                        res.push_str(&format!("extern const char* const {};\n", cpp_name(&e.key)));
+                    }
+                    res.push_str(&format!("}} // namespace {}\n", cpp_fcp_name(*fcp)));
+                }
+                res.push_str("\n} // namespace Mdb\n");
+            }
+            Self::Lookup => {
+                res.push_str("\nenum class FCP {\n");
+                for (fcp, _) in catalogs {
+                    res.push_str(&format!("    {},\n", cpp_fcp_name(*fcp)));
+                }
+                res.push_str("};\n\n/// Message of `key`, or `key` itself when there's none.\n");
+                res.push_str("const char* mdbMessage(FCP fcp, const char* key);\n");
+            }
+        }
+        res
+    }
+
+    /// Source defining what the header declares. Literals are passed through
+    /// `literal` so they match the converted files.
+    pub fn source(
+        self,
+        catalogs: &[(FCP, Vec<&MdbEntry>)],
+        literal: impl Fn(&str) -> String,
+    ) -> String {
+        let mut res = format!("{}#include \"{}.h\"\n", HEADER_NOTE, REFERENCE_FILE);
+        match self {
+            Self::Symbol => {
+                res.push_str("\nnamespace Mdb {\n");
+                for (fcp, entries) in catalogs {
+                    res.push_str(&format!("\nnamespace {} {{\n", cpp_fcp_name(*fcp)));
+                    for e in entries {
+                        res.push_str(&format!(
+                            "const char* const {} = {};\n",
+                            cpp_name(&e.key),
+                            literal(&e.literal)
+                        ));
+                    }
+                    res.push_str(&format!("}} // namespace {}\n", cpp_fcp_name(*fcp)));
+                }
+                res.push_str("\n} // namespace Mdb\n");
+            }
+            Self::Lookup => {
+                res.push_str("\n#include <cstddef>\n#include <cstring>\n\nnamespace {\n\n");
+                res.push_str(
+                    "struct Entry {\n    const char* key;\n    const char* message;\n};\n",
+                );
+                for (fcp, entries) in catalogs {
+                    res.push_str(&format!("\nconst Entry {}[] = {{\n", cpp_fcp_name(*fcp)));
+                    for e in entries {
+                        res.push_str(&format!(
+                            "    {{\"{}\", {}}},\n",
+                            e.key,
+                            literal(&e.literal)
+                        ));
+                    }
+                    res.push_str("};\n");
+                }
+                res.push_str("\n} // namespace\n\n");
+                res.push_str("const char* mdbMessage(FCP fcp, const char* key)\n{\n");
+                res.push_str("    const Entry* entries = nullptr;\n    std::size_t count = 0;\n");
+                res.push_str("    switch (fcp) {\n");
+                for (fcp, _) in catalogs {
+                    let name = cpp_fcp_name(*fcp);
+                    res.push_str(&format!(
+                        "    case FCP::{name}:\n        entries = {name};\n        count = sizeof({name}) / sizeof(Entry);\n        break;\n"
+                    ));
+                }
+                res.push_str("    }\n");
+                res.push_str("    for (std::size_t i = 0; i < count; ++i) {\n");
+                res.push_str("        if (std::strcmp(entries[i].key, key) == 0) {\n");
+                res.push_str("            return entries[i].message;\n        }\n    }\n");
+                res.push_str("    return key;\n}\n");
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loggers(entries: &[(FCP, &str, &str)]) -> HashMap<FCP, Catalog> {
+        let mut loggers: HashMap<FCP, Catalog> = HashMap::new();
+        for (line, (fcp, key, literal)) in entries.iter().enumerate() {
+            let entry = MdbEntry::new(key, literal, "t.mdb", line + 1);
+            loggers
+                .entry(*fcp)
+                .or_default()
+                .insert(key.to_string(), entry);
+        }
+        loggers
+    }
+
+    fn sample() -> HashMap<FCP, Catalog> {
+        loggers(&[
+            (FCP::SE, "Err1", r#""Net \"%s\", pin %d""#),
+            (FCP::ASM, "union", r#""50%% done""#),
+            (FCP::ASM, "Err2", r#""a,b\nc""#),
+        ])
+    }
+
+    #[test]
+    fn renames_every_keyword() {
+        for keyword in CPP_KEYWORDS {
+            assert_eq!(cpp_name(keyword), format!("{}_", keyword));
+        }
+        for keyword in [
+            "union", "register", "template", "operator", "and", "or", "not",
+        ] {
+            assert!(CPP_KEYWORDS.contains(&keyword));
+        }
+        for keyword in [
+            "signed", "unsigned", "inline", "virtual", "typename", "sizeof",
+        ] {
+            assert!(CPP_KEYWORDS.contains(&keyword));
+        }
+        assert_eq!(cpp_name("Err1"), "Err1");
+        assert_eq!(cpp_name("final"), "final");
+    }
+
+    #[test]
+    fn writes_csv_with_quoted_fields() {
+        let csv = export(&sample(), ExportFormat::Csv).unwrap();
+        assert_eq!(
+            csv,
+            "fcp,key,message,args\n\
+             fcpse,Err1,\"Net \"\"%s\"\", pin %d\",%s %d\n\
+             fcpasm,union,50%% done,\n\
+             fcpasm,Err2,\"a,b\nc\",\n"
+        );
+    }
+
+    #[test]
+    fn writes_yaml_with_json_scalars() {
+        let yaml = export(&sample(), ExportFormat::Yaml).unwrap();
+        assert!(yaml.starts_with(
+            "- fcp: \"fcpse\"\n  key: \"Err1\"\n  message: \"Net \\\"%s\\\", pin %d\"\n  args:\n    - spec: \"%s\"\n      type: string\n    - spec: \"%d\"\n      type: int\n"
+        ));
+        assert!(yaml
+            .ends_with("- fcp: \"fcpasm\"\n  key: \"Err2\"\n  message: \"a,b\\nc\"\n  args: []\n"));
+    }
+
+    #[test]
+    fn writes_headers_with_safe_names() {
+        let header = export(&sample(), ExportFormat::Header).unwrap();
+        assert!(header.contains(
+            "namespace ASM {\nconstexpr const char* union_ = \"50%% done\";\nconstexpr const char* Err2 = \"a,b\\nc\";\n} // namespace ASM\n"
+        ));
+        assert!(header.find("namespace SE").unwrap() < header.find("namespace ASM").unwrap());
+
+        let enums = export(&sample(), ExportFormat::Enum).unwrap();
+        assert!(enums.contains("enum class ASM {\n    union_,\n    Err2,\n};\n"));
+        assert!(enums.contains("    case ASM::union_: return \"50%% done\";\n"));
+    }
+}
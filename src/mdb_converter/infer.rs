@@ -0,0 +1,205 @@
+use regex::Regex;
+use std::path::Path;
+
+use super::FCP;
+
+/// Scores of every loaded `FCP` for one .cpp file, best first.
+#[derive(Debug)]
+pub struct Inference {
+    pub scores: Vec<(FCP, u32, Vec<String>)>,
+}
+
+impl Inference {
+    /// The best `FCP` when it clearly stands out, `None` when prompting is
+    /// still needed.
+    pub fn confident(&self) -> Option<FCP> {
+        let (best, score, _) = self.scores.first()?;
+        let second = self.scores.get(1).map_or(0, |s| s.1);
+        (*score >= 4 && *score >= 2 * second).then_some(*best)
+    }
+}
+
+/// How strongly `words` point at `fcp`: 2 for `fcpasm`/`Fcp Asm`, 1 for a
+/// bare `asm`.
+fn word_match(words: &[String], fcp: FCP) -> u32 {
+    let full = fcp.to_str();
+    let short = full.trim_start_matches("fcp");
+
+    let strong = words.contains(&full) || words.windows(2).any(|w| w[0] == "fcp" && w[1] == short);
+    if strong {
+        2
+    } else if words.iter().any(|w| w == short) {
+        1
+    } else {
+        0
+    }
+}
+
+/// Lowercase words of an identifier or path, split on separators and
+/// camelCase boundaries.
+fn words(s: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut prev_lower = false;
+    for c in s.chars() {
+        if !c.is_alphanumeric() {
+            prev_lower = false;
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if c.is_uppercase() && prev_lower {
+            words.push(std::mem::take(&mut word));
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+pub fn infer_fcp(file_name: &str, buffer: &str, fcps: &[FCP]) -> Inference {
+    let path = Path::new(file_name);
+    let stem = words(&path.file_stem().unwrap_or_default().to_string_lossy());
+    let dirs = words(&path.parent().unwrap_or(Path::new("")).to_string_lossy());
+
+    let include_re = Regex::new(r#"#\s*include\s*[<"](?P<Path>[^>"]+)[>"]"#).unwrap();
+    let includes: Vec<Vec<String>> = include_re
+        .captures_iter(buffer)
+        .map(|c| words(&c["Path"]))
+        .collect();
+
+    let namespace_re = Regex::new(r"\bnamespace\s+(?P<Name>[\w:]+)").unwrap();
+    let namespaces: Vec<Vec<String>> = namespace_re
+        .captures_iter(buffer)
+        .map(|c| words(&c["Name"]))
+        .collect();
+
+    let logger_re = Regex::new(r"\b(?P<Name>\w*(?:Log|log|LOG)\w*)\s*[(={;]").unwrap();
+    let loggers: Vec<Vec<String>> = logger_re
+        .captures_iter(buffer)
+        .map(|c| words(&c["Name"]))
+        .collect();
+
+    let mut scores = vec![];
+    for fcp in fcps {
+        let mut score = 0;
+        let mut reasons = vec![];
+
+        // a bare short name like `ui` or `me` turns up in unrelated names,
+        // so it only backs up other evidence
+        let m = word_match(&stem, *fcp);
+        if m > 0 {
+            score += 3 * m;
+            reasons.push("file name".to_string());
+        }
+        let m = word_match(&dirs, *fcp);
+        if m > 0 {
+            score += 2 * m - 1;
+            reasons.push("directory".to_string());
+        }
+        for (what, found, strong, cap) in [
+            ("include", &includes, 4, 4),
+            ("namespace", &namespaces, 4, 4),
+            ("logger", &loggers, 2, 3),
+        ] {
+            let m: u32 = found
+                .iter()
+                .map(|w| match word_match(w, *fcp) {
+                    2 => strong,
+                    m => m,
+                })
+                .sum();
+            if m > 0 {
+                score += m.min(cap);
+                reasons.push(what.to_string());
+            }
+        }
+
+        if score > 0 {
+            scores.push((*fcp, score, reasons));
+        }
+    }
+    scores.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    Inference { scores }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FCPS: [FCP; 6] = [FCP::SE, FCP::ASM, FCP::DP, FCP::IO, FCP::ME, FCP::UI];
+
+    fn confident(file_name: &str, buffer: &str) -> Option<FCP> {
+        infer_fcp(file_name, buffer, &FCPS).confident()
+    }
+
+    fn inference(scores: &[(FCP, u32)]) -> Inference {
+        Inference {
+            scores: scores.iter().map(|&(fcp, s)| (fcp, s, vec![])).collect(),
+        }
+    }
+
+    #[test]
+    fn splits_words_on_case_and_separators() {
+        assert_eq!(words("src/FcpAsm.cpp"), ["src", "fcp", "asm", "cpp"]);
+        assert_eq!(
+            words("ui_mainWindow2D.h"),
+            ["ui", "main", "window2", "d", "h"]
+        );
+    }
+
+    #[test]
+    fn file_name_decides() {
+        assert_eq!(confident("src/FcpAsm.cpp", ""), Some(FCP::ASM));
+        assert_eq!(confident("fcpse_nets.cpp", ""), Some(FCP::SE));
+        let inference = infer_fcp("src/FcpAsm.cpp", "", &FCPS);
+        assert_eq!(inference.scores[0].2, ["file name"]);
+    }
+
+    #[test]
+    fn include_of_an_fcp_decides() {
+        let buffer = "#include \"fcpasm/Package.h\"\n#include <QString>\n";
+        assert_eq!(confident("src/Package.cpp", buffer), Some(FCP::ASM));
+    }
+
+    #[test]
+    fn short_names_alone_are_not_enough() {
+        assert_eq!(confident("src/ReadMe.cpp", ""), None);
+        assert_eq!(confident("src/ui/Main.cpp", ""), None);
+        let buffer = "#include \"ui_main.h\"\n#include <io/stream.h>\nnamespace dp {}\n";
+        assert_eq!(confident("src/Main.cpp", buffer), None);
+        let buffer = "QFile ioLog(path);\nQDialog dialog(this);\n";
+        assert_eq!(confident("src/Main.cpp", buffer), None);
+    }
+
+    #[test]
+    fn short_names_back_each_other_up() {
+        assert_eq!(confident("src/ui/MainUi.cpp", ""), Some(FCP::UI));
+        let buffer = "#include \"io/stream.h\"\nauto ioLog = MdbLogger(\"io\");\n";
+        assert_eq!(confident("src/io/IoReader.cpp", buffer), Some(FCP::IO));
+    }
+
+    #[test]
+    fn conflicting_evidence_asks() {
+        let buffer = "#include \"fcpse/Nets.h\"\n#include \"fcpasm/Package.h\"\n";
+        assert_eq!(confident("src/Main.cpp", buffer), None);
+        assert_eq!(confident("src/FcpAsm.cpp", buffer), Some(FCP::ASM));
+    }
+
+    #[test]
+    fn confidence_needs_four_points_and_twice_the_runner_up() {
+        assert_eq!(inference(&[(FCP::ASM, 4)]).confident(), Some(FCP::ASM));
+        assert_eq!(inference(&[(FCP::ASM, 3)]).confident(), None);
+        assert_eq!(
+            inference(&[(FCP::ASM, 4), (FCP::SE, 2)]).confident(),
+            Some(FCP::ASM)
+        );
+        assert_eq!(inference(&[(FCP::ASM, 5), (FCP::SE, 3)]).confident(), None);
+        assert_eq!(inference(&[]).confident(), None);
+    }
+}
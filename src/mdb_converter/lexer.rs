@@ -0,0 +1,282 @@
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TokenKind {
+    Ident,
+    Number,
+    Str,
+    Char,
+    Comment,
+    Punct,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// Byte offset in the tokenized source.
+    pub start: usize,
+}
+
+impl Token<'_> {
+    pub fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    pub fn is(&self, punct: &str) -> bool {
+        self.kind == TokenKind::Punct && self.text == punct
+    }
+
+    pub fn opens(&self) -> bool {
+        self.kind == TokenKind::Punct && matches!(self.text, "(" | "[" | "{")
+    }
+
+    pub fn closes(&self) -> bool {
+        self.kind == TokenKind::Punct && matches!(self.text, ")" | "]" | "}")
+    }
+
+    /// Contents of a string literal without prefix and quotes.
+    pub fn str_contents(&self) -> Option<&str> {
+        if self.kind != TokenKind::Str {
+            return None;
+        }
+        let quote = self.text.find('"')?;
+        let body = &self.text[quote + 1..];
+        if self.text[..quote].ends_with('R') {
+            let open = body.find('(')?;
+            let close = body.rfind(')')?;
+            return Some(&body[open + 1..close]);
+        }
+        Some(body.strip_suffix('"').unwrap_or(body))
+    }
+}
+
+const PUNCTS: [&str; 29] = [
+    "<<=", ">>=", "...", "->*", "<=>", "<<", ">>", "::", "->", "++", "--", "&&", "||", "==", "!=",
+    "<=", ">=", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", ".*", "##", "<:", ":>",
+];
+
+/// Splits C++ source into tokens. Whitespace is dropped, comments are kept.
+/// Unterminated literals and comments run to the end of the input.
+pub fn tokenize(src: &str) -> Vec<Token<'_>> {
+    let bytes = src.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+
+        let kind = if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        } else if src[i..].starts_with("//") {
+            i = src[i..].find('\n').map_or(bytes.len(), |n| i + n);
+            TokenKind::Comment
+        } else if src[i..].starts_with("/*") {
+            i = src[i + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |n| i + 2 + n + 2);
+            TokenKind::Comment
+        } else if let Some(prefix) = literal_prefix(&src[i..]) {
+            i += prefix;
+            if bytes[i] == b'\'' {
+                i = skip_quoted(bytes, i, b'\'');
+                TokenKind::Char
+            } else if src[start..i].ends_with('R') {
+                i = skip_raw(src, i);
+                TokenKind::Str
+            } else {
+                i = skip_quoted(bytes, i, b'"');
+                TokenKind::Str
+            }
+        } else if c.is_ascii_alphabetic() || c == b'_' || c >= 0x80 {
+            while i < bytes.len()
+                && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] >= 0x80)
+            {
+                i += 1;
+            }
+            TokenKind::Ident
+        } else if c.is_ascii_digit()
+            || (c == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+        {
+            i += 1;
+            while i < bytes.len() {
+                let b = bytes[i];
+                let exponent_sign =
+                    (b == b'+' || b == b'-') && matches!(bytes[i - 1], b'e' | b'E' | b'p' | b'P');
+                if !(exponent_sign || b.is_ascii_alphanumeric() || b"_.'".contains(&b)) {
+                    break;
+                }
+                i += 1;
+            }
+            TokenKind::Number
+        } else {
+            let len = PUNCTS
+                .iter()
+                .find(|p| src[i..].starts_with(*p))
+                .map_or(1, |p| p.len());
+            i += len;
+            TokenKind::Punct
+        };
+
+        // an escape in an unterminated literal may stop inside a character
+        while !src.is_char_boundary(i) {
+            i += 1;
+        }
+        tokens.push(Token {
+            kind,
+            text: &src[start..i],
+            start,
+        });
+    }
+
+    tokens
+}
+
+/// Length of the encoding prefix when `s` starts a string or char literal.
+fn literal_prefix(s: &str) -> Option<usize> {
+    for prefix in ["u8R", "LR", "uR", "UR", "R", "u8", "L", "u", "U", ""] {
+        if let Some(rest) = s.strip_prefix(prefix) {
+            let raw = prefix.ends_with('R');
+            if rest.starts_with('"') || (!raw && rest.starts_with('\'')) {
+                return Some(prefix.len());
+            }
+        }
+    }
+    None
+}
+
+fn skip_quoted(bytes: &[u8], mut i: usize, quote: u8) -> usize {
+    i += 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'\n' => return i,
+            b if b == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn skip_raw(src: &str, i: usize) -> usize {
+    let Some(open) = src[i..].find('(') else {
+        return src.len();
+    };
+    let delim = &src[i + 1..i + open];
+    let close = format!("){}\"", delim);
+    src[i + open..]
+        .find(&close)
+        .map_or(src.len(), |n| i + open + n + close.len())
+}
+
+/// Splits `tokens` at every `sep` outside brackets. Comments are dropped from
+/// the resulting groups.
+pub fn split_top_level<'a>(tokens: &[Token<'a>], sep: &str) -> Vec<Vec<Token<'a>>> {
+    let mut groups = vec![vec![]];
+    let mut depth = 0usize;
+
+    for token in tokens {
+        if token.opens() {
+            depth += 1;
+        } else if token.closes() {
+            depth = depth.saturating_sub(1);
+        } else if depth == 0 && token.is(sep) {
+            groups.push(vec![]);
+            continue;
+        }
+        if token.kind != TokenKind::Comment {
+            groups.last_mut().unwrap().push(*token);
+        }
+    }
+
+    groups
+}
+
+/// Index of the bracket closing the one at `open`.
+pub fn matching_close(tokens: &[Token], open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        if token.opens() {
+            depth += 1;
+        } else if token.closes() {
+            depth = depth.saturating_sub(1);
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds_and_texts(src: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(src).iter().map(|t| (t.kind, t.text)).collect()
+    }
+
+    #[test]
+    fn splits_punctuation_greedily() {
+        let texts: Vec<&str> = tokenize("a<<=b->c::d<<e").iter().map(|t| t.text).collect();
+        assert_eq!(texts, ["a", "<<=", "b", "->", "c", "::", "d", "<<", "e"]);
+    }
+
+    #[test]
+    fn keeps_comments_and_literals_whole() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds_and_texts("x /* a;b */ << \"q\\\"<<\" << 'c' // end;\n1.5e+3"),
+            [
+                (Ident, "x"),
+                (Comment, "/* a;b */"),
+                (Punct, "<<"),
+                (Str, "\"q\\\"<<\""),
+                (Punct, "<<"),
+                (Char, "'c'"),
+                (Comment, "// end;"),
+                (Number, "1.5e+3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_raw_and_prefixed_strings() {
+        let tokens = tokenize(r#"u8"a" R"x(b)")x" L'c'"#);
+        let texts: Vec<&str> = tokens.iter().map(|t| t.text).collect();
+        assert_eq!(texts, ["u8\"a\"", "R\"x(b)\")x\"", "L'c'"]);
+        assert_eq!(tokens[1].str_contents(), Some("b)\""));
+        assert_eq!(tokens[0].str_contents(), Some("a"));
+    }
+
+    #[test]
+    fn unterminated_literal_stops_at_line_end() {
+        let tokens = tokenize("\"abc\nx");
+        assert_eq!(tokens[0].text, "\"abc");
+        assert_eq!(tokens[1].text, "x");
+    }
+
+    #[test]
+    fn splits_only_outside_brackets() {
+        let tokens = tokenize("a << f(b << c) /* x */ << d[1 << 2]");
+        let groups: Vec<Vec<&str>> = split_top_level(&tokens, "<<")
+            .iter()
+            .map(|g| g.iter().map(|t| t.text).collect())
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                vec!["a"],
+                vec!["f", "(", "b", "<<", "c", ")"],
+                vec!["d", "[", "1", "<<", "2", "]"],
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_matching_bracket() {
+        let tokens = tokenize("f(a(b), [c])");
+        assert_eq!(matching_close(&tokens, 1), Some(tokens.len() - 1));
+        assert_eq!(matching_close(&tokenize("f(a"), 1), None);
+    }
+}
@@ -0,0 +1,347 @@
+use regex::Regex;
+use serde::Serialize;
+
+use super::encoding::MdbEncoding;
+use super::error::Error;
+use super::mdb_entry::{parse_format, unescape};
+use super::mdb_grammar::RawEntry;
+use super::mdb_parser::{load_mdb, DuplicatePolicy};
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub enum LintFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl LintFormat {
+    pub const NAMES: [&'static str; 2] = ["text", "json"];
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// One problem found in an mdb file. `line` is 0 for problems with the
+/// file as a whole.
+#[derive(Serialize, Debug)]
+pub struct LintIssue {
+    pub file: String,
+    pub line: usize,
+    pub column: Option<usize>,
+    pub check: &'static str,
+    pub message: String,
+}
+
+impl LintIssue {
+    fn new(file: &str, line: usize, check: &'static str, message: String) -> Self {
+        Self {
+            file: file.to_string(),
+            line,
+            column: None,
+            check,
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:", self.file, self.line)?;
+        if let Some(column) = self.column {
+            write!(f, "{}:", column)?;
+        }
+        write!(f, " {}: {}", self.check, self.message)
+    }
+}
+
+pub fn lint(paths: &[String], encoding: MdbEncoding) -> Vec<LintIssue> {
+    paths
+        .iter()
+        .flat_map(|path| lint_file(path, encoding))
+        .collect()
+}
+
+/// Checks one mdb file with the same loader the converter uses, so every
+/// problem the converter would stumble over is reported.
+pub fn lint_file(path: &str, encoding: MdbEncoding) -> Vec<LintIssue> {
+    let mut issues = vec![];
+
+    let file = match load_mdb(path, encoding, DuplicatePolicy::Error) {
+        Ok(file) => file,
+        Err(e) => return vec![LintIssue::new(path, 0, "read", e.to_string())],
+    };
+
+    if let Some(issue) = check_utf8(path, file.encoding) {
+        issues.push(issue);
+    }
+
+    for e in file.errors.iter().chain(&file.warnings) {
+        let issue = match e {
+            Error::MdbSyntax {
+                line,
+                column,
+                message,
+                ..
+            } => LintIssue {
+                column: Some(*column),
+                ..LintIssue::new(path, *line, "syntax", message.clone())
+            },
+            Error::DuplicateKey {
+                key,
+                first_line,
+                line,
+                ..
+            } => {
+                let msg = format!("{} is already defined on line {}", key, first_line);
+                LintIssue::new(path, *line, "duplicate-key", msg)
+            }
+            Error::StrayQuotes { line, key, .. } => {
+                let msg = format!("unescaped quotes in the message of {}", key);
+                LintIssue::new(path, *line, "unbalanced-quotes", msg)
+            }
+            e => LintIssue::new(path, 0, "read", e.to_string()),
+        };
+        issues.push(issue);
+    }
+
+    let severity_re = severity_re();
+    for entry in &file.entries {
+        issues.extend(check_entry(path, entry, &severity_re));
+    }
+
+    issues.sort_by_key(|i| (i.line, i.column));
+    issues
+}
+
+fn check_utf8(path: &str, used: MdbEncoding) -> Option<LintIssue> {
+    let bytes = std::fs::read(path).ok()?;
+    let e = std::str::from_utf8(&bytes).err()?;
+
+    let valid = &bytes[..e.valid_up_to()];
+    let line = valid.iter().filter(|&&b| b == b'\n').count() + 1;
+    let line_start = valid.iter().rposition(|&b| b == b'\n').map_or(0, |n| n + 1);
+    let column = String::from_utf8_lossy(&valid[line_start..])
+        .chars()
+        .count()
+        + 1;
+
+    let msg = format!("not valid UTF-8, read as {}", used.to_str());
+    Some(LintIssue {
+        column: Some(column),
+        ..LintIssue::new(path, line, "non-utf8", msg)
+    })
+}
+
+/// A severity word at the start of a message, up to its colon.
+fn severity_re() -> Regex {
+    Regex::new(r"(?i)^\s*(?P<Prefix>(?P<Word>error|err|warning|warn|info|information|note)\s*:)")
+        .unwrap()
+}
+
+fn check_entry(path: &str, entry: &RawEntry, severity_re: &Regex) -> Vec<LintIssue> {
+    let mut issues = vec![];
+    let body = &entry.literal[1..entry.literal.len() - 1];
+
+    for escape in invalid_escapes(body) {
+        let msg = format!("invalid escape sequence `{}` in {}", escape, entry.key);
+        issues.push(LintIssue::new(path, entry.line, "invalid-escape", msg));
+    }
+
+    let message = unescape(body);
+    for spec in parse_format(&message) {
+        if spec.kind().is_none() {
+            let msg = format!(
+                "malformed printf conversion `{}` in {}",
+                spec.text, entry.key
+            );
+            issues.push(LintIssue::new(path, entry.line, "bad-format", msg));
+        }
+    }
+
+    if let Some(cap) = severity_re.captures(&message) {
+        let canonical = match cap["Word"].to_lowercase().as_str() {
+            "error" | "err" => "Error:",
+            "warning" | "warn" => "Warning:",
+            _ => "Info:",
+        };
+        let prefix = &cap["Prefix"];
+        if prefix != canonical {
+            let msg = format!(
+                "severity prefix `{}` of {} should be `{}`",
+                prefix, entry.key, canonical
+            );
+            issues.push(LintIssue::new(path, entry.line, "severity-prefix", msg));
+        }
+    }
+
+    if let Some(c) = entry.separator.chars().find(|c| !matches!(c, ' ' | '\t')) {
+        let msg = format!(
+            "key {} is followed by unusual whitespace U+{:04X}",
+            entry.key, c as u32
+        );
+        issues.push(LintIssue::new(path, entry.line, "key-whitespace", msg));
+    }
+
+    issues
+}
+
+/// Escape sequences of a C string literal body that C doesn't define.
+fn invalid_escapes(body: &str) -> Vec<String> {
+    let mut res = vec![];
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            continue;
+        }
+        let Some(e) = chars.next() else {
+            res.push("\\".to_string());
+            break;
+        };
+        let hex_digits = match e {
+            'n' | 't' | 'r' | 'a' | 'b' | 'f' | 'v' | '\\' | '\'' | '"' | '?' | '0'..='7' => {
+                continue
+            }
+            'x' => 1..=usize::MAX,
+            'u' => 4..=4,
+            'U' => 8..=8,
+            e => {
+                res.push(format!("\\{}", e));
+                continue;
+            }
+        };
+
+        let mut escape = format!("\\{}", e);
+        while let Some(h) = chars.peek().filter(|h| h.is_ascii_hexdigit()) {
+            if escape.len() - 2 == *hex_digits.end() {
+                break;
+            }
+            escape.push(*h);
+            chars.next();
+        }
+        if !hex_digits.contains(&(escape.len() - 2)) {
+            res.push(escape);
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(literal: &str) -> RawEntry {
+        RawEntry {
+            key: "Err1".to_string(),
+            literal: literal.to_string(),
+            line: 3,
+            separator: " ".to_string(),
+            stray_quotes: false,
+        }
+    }
+
+    fn checks(literal: &str) -> Vec<String> {
+        check_entry("t.mdb", &entry(literal), &severity_re())
+            .into_iter()
+            .map(|i| format!("{}: {}", i.check, i.message))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_c_escapes() {
+        assert!(invalid_escapes(r#"\n\t\r\a\b\f\v\\\'\"\?\0\177"#).is_empty());
+        assert!(invalid_escapes(r"\x4 \x41 \xDeadBeef").is_empty());
+        assert!(invalid_escapes(r"é éf \U0001F600 \U0001F6000").is_empty());
+    }
+
+    #[test]
+    fn reports_escapes_c_doesnt_define() {
+        assert_eq!(invalid_escapes(r"\q \% \e"), [r"\q", r"\%", r"\e"]);
+        assert_eq!(invalid_escapes(r"\x \xg"), [r"\x", r"\x"]);
+        assert_eq!(invalid_escapes(r"\u12 \u123g"), [r"\u12", r"\u123"]);
+        assert_eq!(invalid_escapes(r"\U0001F60 \U"), [r"\U0001F60", r"\U"]);
+        assert_eq!(invalid_escapes(r"end\"), [r"\"]);
+    }
+
+    #[test]
+    fn normalises_severity_prefixes() {
+        assert!(checks(r#""Error: x""#).is_empty());
+        assert!(checks(r#""Warning: x""#).is_empty());
+        assert!(checks(r#""Info: x""#).is_empty());
+        assert!(checks(r#""Errors: x""#).is_empty());
+        assert_eq!(
+            checks(r#""  err : x""#),
+            ["severity-prefix: severity prefix `err :` of Err1 should be `Error:`"]
+        );
+        assert_eq!(
+            checks(r#""WARN: x""#),
+            ["severity-prefix: severity prefix `WARN:` of Err1 should be `Warning:`"]
+        );
+        assert_eq!(
+            checks(r#""information: x""#),
+            ["severity-prefix: severity prefix `information:` of Err1 should be `Info:`"]
+        );
+        assert_eq!(
+            checks(r#""Note: x""#),
+            ["severity-prefix: severity prefix `Note:` of Err1 should be `Info:`"]
+        );
+    }
+
+    #[test]
+    fn reports_formats_escapes_and_separators() {
+        assert_eq!(
+            checks(r#""\q 50%""#),
+            [
+                r"invalid-escape: invalid escape sequence `\q` in Err1",
+                "bad-format: malformed printf conversion `%` in Err1",
+            ]
+        );
+        let mut odd = entry(r#""x""#);
+        odd.separator = " \u{a0}".to_string();
+        let issues = check_entry("t.mdb", &odd, &Regex::new("^$").unwrap());
+        assert_eq!(issues[0].check, "key-whitespace");
+        assert_eq!(
+            issues[0].message,
+            "key Err1 is followed by unusual whitespace U+00A0"
+        );
+    }
+
+    #[test]
+    fn reports_issues_as_json() {
+        let dir = std::env::temp_dir().join(format!("lint-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("fcpasm.mdb");
+        std::fs::write(&path, "Err1 \"warn: a\"\n1Err \"b\"\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+        let issues = lint_file(&path, MdbEncoding::Auto);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let json = serde_json::to_value(&issues).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {
+                    "file": path,
+                    "line": 1,
+                    "column": null,
+                    "check": "severity-prefix",
+                    "message": "severity prefix `warn:` of Err1 should be `Warning:`",
+                },
+                {
+                    "file": path,
+                    "line": 2,
+                    "column": 1,
+                    "check": "syntax",
+                    "message": "expected a key",
+                },
+            ])
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+/// All entries of one .mdb file, by key.
+pub type Catalog = HashMap<String, MdbEntry>;
+
+#[derive(Clone, Debug)]
+pub struct MdbEntry {
+    pub key: String,
+    /// Quoted literal exactly as written in the .mdb file.
+    pub literal: String,
+    /// Literal with quotes stripped and escapes resolved.
+    pub message: String,
+    pub file: String,
+    pub line: usize,
+    pub specs: Vec<FormatSpec>,
+}
+
+impl MdbEntry {
+    pub fn new(key: &str, literal: &str, file: &str, line: usize) -> Self {
+        let inner = literal.strip_prefix('"').unwrap_or(literal);
+        let inner = inner.strip_suffix('"').unwrap_or(inner);
+        let message = unescape(inner);
+        let specs = parse_format(&message);
+        Self {
+            key: key.to_string(),
+            literal: literal.to_string(),
+            message,
+            file: file.to_string(),
+            line,
+            specs,
+        }
+    }
+
+    /// Number of arguments the message expects, `*` widths included.
+    pub fn arg_count(&self) -> usize {
+        self.specs.iter().map(|s| s.arg_count()).sum()
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Count {
+    Fixed(usize),
+    Star,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ArgKind {
+    Int,
+    Float,
+    Char,
+    Str,
+    Pointer,
+    Written,
+    Percent,
+}
+
+/// One printf conversion such as `%-15s` or `%11.2f`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FormatSpec {
+    pub text: String,
+    /// Byte offset of `%` in the unescaped message.
+    pub offset: usize,
+    pub flags: String,
+    pub width: Option<Count>,
+    pub precision: Option<Count>,
+    pub length: String,
+    pub conversion: char,
+}
+
+impl FormatSpec {
+    pub fn kind(&self) -> Option<ArgKind> {
+        let kind = match self.conversion {
+            'd' | 'i' | 'u' | 'o' | 'x' | 'X' => ArgKind::Int,
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' | 'a' | 'A' => ArgKind::Float,
+            'c' => ArgKind::Char,
+            's' => ArgKind::Str,
+            'p' => ArgKind::Pointer,
+            'n' => ArgKind::Written,
+            '%' => ArgKind::Percent,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    pub fn arg_count(&self) -> usize {
+        if self.conversion == '%' {
+            return 0;
+        }
+        let stars = [self.width, self.precision]
+            .iter()
+            .filter(|c| **c == Some(Count::Star))
+            .count();
+        stars + 1
+    }
+}
+
+/// Collects printf conversions of `message`. A `%` that doesn't start a
+/// complete conversion is kept with whatever was read, so callers can
+/// recognise it through [`FormatSpec::kind`].
+pub fn parse_format(message: &str) -> Vec<FormatSpec> {
+    let mut specs = vec![];
+    let chars: Vec<(usize, char)> = message.char_indices().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (offset, c) = chars[i];
+        i += 1;
+        if c != '%' {
+            continue;
+        }
+
+        let mut spec = FormatSpec {
+            text: String::new(),
+            offset,
+            flags: String::new(),
+            width: None,
+            precision: None,
+            length: String::new(),
+            conversion: '\0',
+        };
+
+        while i < chars.len() && "-+ #0".contains(chars[i].1) {
+            spec.flags.push(chars[i].1);
+            i += 1;
+        }
+        spec.width = read_count(&chars, &mut i);
+        if i < chars.len() && chars[i].1 == '.' {
+            i += 1;
+            spec.precision = Some(read_count(&chars, &mut i).unwrap_or(Count::Fixed(0)));
+        }
+        while i < chars.len() && "hlLqjzt".contains(chars[i].1) {
+            spec.length.push(chars[i].1);
+            i += 1;
+        }
+        if i < chars.len() {
+            spec.conversion = chars[i].1;
+            i += 1;
+        }
+
+        let end = chars.get(i).map_or(message.len(), |(o, _)| *o);
+        spec.text = message[offset..end].to_string();
+        specs.push(spec);
+    }
+
+    specs
+}
+
+fn read_count(chars: &[(usize, char)], i: &mut usize) -> Option<Count> {
+    if *i < chars.len() && chars[*i].1 == '*' {
+        *i += 1;
+        return Some(Count::Star);
+    }
+    let mut digits = String::new();
+    while *i < chars.len() && chars[*i].1.is_ascii_digit() {
+        digits.push(chars[*i].1);
+        *i += 1;
+    }
+    digits.parse().ok().map(Count::Fixed)
+}
+
+/// Resolves C escape sequences. Unknown escapes keep the escaped character.
+pub fn unescape(s: &str) -> String {
+    let mut res = String::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        let Some(e) = chars.next() else {
+            res.push('\\');
+            break;
+        };
+        match e {
+            'n' => res.push('\n'),
+            't' => res.push('\t'),
+            'r' => res.push('\r'),
+            'a' => res.push('\x07'),
+            'b' => res.push('\x08'),
+            'f' => res.push('\x0c'),
+            'v' => res.push('\x0b'),
+            'x' => {
+                let mut hex = String::new();
+                while let Some(h) = chars.peek().filter(|h| h.is_ascii_hexdigit()) {
+                    hex.push(*h);
+                    chars.next();
+                }
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => res.push(c),
+                    None => res.push_str(&format!("\\x{hex}")),
+                }
+            }
+            '0'..='7' => {
+                let mut oct = e.to_string();
+                while let Some(o) = chars.peek().filter(|o| ('0'..='7').contains(o)) {
+                    if oct.len() == 3 {
+                        break;
+                    }
+                    oct.push(*o);
+                    chars.next();
+                }
+                let code = u32::from_str_radix(&oct, 8).unwrap_or(0);
+                res.push(char::from_u32(code).unwrap_or('\0'));
+            }
+            e => res.push(e),
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_every_part_of_a_conversion() {
+        let specs = parse_format("%-15s|%*.*lf|%.d|100%%");
+        let texts: Vec<&str> = specs.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, ["%-15s", "%*.*lf", "%.d", "%%"]);
+
+        assert_eq!(specs[0].offset, 0);
+        assert_eq!(specs[0].flags, "-");
+        assert_eq!(specs[0].width, Some(Count::Fixed(15)));
+        assert_eq!(specs[1].width, Some(Count::Star));
+        assert_eq!(specs[1].precision, Some(Count::Star));
+        assert_eq!(specs[1].length, "l");
+        assert_eq!(specs[1].conversion, 'f');
+        assert_eq!(specs[2].precision, Some(Count::Fixed(0)));
+        assert_eq!(specs[3].kind(), Some(ArgKind::Percent));
+    }
+
+    #[test]
+    fn keeps_incomplete_conversions() {
+        let specs = parse_format("50%");
+        assert_eq!(specs.len(), 1);
+        assert_eq!(specs[0].text, "%");
+        assert_eq!(specs[0].kind(), None);
+        assert_eq!(parse_format("%y")[0].kind(), None);
+    }
+
+    #[test]
+    fn counts_star_arguments() {
+        let entry = MdbEntry::new("Err1", r#""%*.*f \"%s\" %d%%""#, "t.mdb", 1);
+        assert_eq!(entry.message, "%*.*f \"%s\" %d%%");
+        assert_eq!(entry.arg_count(), 5);
+    }
+}
@@ -0,0 +1,333 @@
+//! Line grammar of .mdb files:
+//!
+//! ```text
+//! file    = { line }
+//! line    = blank | comment | entry
+//! comment = ws ( "#" | "//" ) any
+//! entry   = ws key ws1 message { ws message } ws [ "//" any ]
+//! key     = ( letter | "_" ) { letter | digit | "_" }
+//! message = '"' { char | "\" any-char } '"'
+//! ```
+//!
+//! Line endings may be CRLF. A backslash right before a line break joins the
+//! two lines, as in C, so long messages can be continued on the next line.
+
+use rayon::prelude::*;
+
+use super::error::Error;
+
+/// One character of the spliced text with its physical position, both
+/// 1-based.
+#[derive(Copy, Clone)]
+struct Char {
+    c: char,
+    line: usize,
+    column: usize,
+}
+
+/// An entry as written in the file. Adjacent messages are joined into one
+/// literal.
+#[derive(Debug, Clone)]
+pub struct RawEntry {
+    pub key: String,
+    pub literal: String,
+    pub line: usize,
+    /// Whitespace between the key and the message.
+    pub separator: String,
+    /// The message had unescaped quotes inside and was read from the first
+    /// to the last quote of the line, the way older tools did.
+    pub stray_quotes: bool,
+}
+
+/// Parses `text` into entries, collecting a diagnostic for every line that
+/// doesn't follow the grammar.
+pub fn parse_mdb(text: &str, path: &str) -> (Vec<RawEntry>, Vec<Error>) {
+    let chars = splice(text);
+    let lines: Vec<&[Char]> = chars.split(|c| c.c == '\n').collect();
+
+    let parsed: Vec<Option<Result<RawEntry, Error>>> = lines
+        .par_iter()
+        .map(|line| parse_line(line, path))
+        .collect();
+
+    let mut entries = vec![];
+    let mut errors = vec![];
+    for line in parsed.into_iter().flatten() {
+        match line {
+            Ok(entry) => entries.push(entry),
+            Err(e) => errors.push(e),
+        }
+    }
+    (entries, errors)
+}
+
+/// Drops carriage returns before line breaks and backslash-newline pairs,
+/// keeping the position of every remaining character.
+fn splice(text: &str) -> Vec<Char> {
+    let mut res = vec![];
+    let mut line = 1;
+    let mut column = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let mut next = chars.peek().copied();
+        if c == '\r' && (next == Some('\n') || next.is_none()) {
+            column += 1;
+            continue;
+        }
+        if c == '\\' {
+            if next == Some('\r') {
+                let mut ahead = chars.clone();
+                ahead.next();
+                if ahead.peek() == Some(&'\n') {
+                    chars.next();
+                    next = Some('\n');
+                }
+            }
+            if next == Some('\n') {
+                chars.next();
+                line += 1;
+                column = 1;
+                continue;
+            }
+        }
+
+        res.push(Char { c, line, column });
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    res
+}
+
+fn parse_line(line: &[Char], path: &str) -> Option<Result<RawEntry, Error>> {
+    let mut i = skip_ws(line, 0);
+    if i == line.len() || line[i].c == '#' || starts_with(line, i, "//") {
+        return None;
+    }
+
+    let error = |i: usize, message: &str| {
+        let at = line.get(i).or(line.last()).unwrap();
+        let column = if i < line.len() {
+            at.column
+        } else {
+            at.column + 1
+        };
+        Some(Err(Error::MdbSyntax {
+            path: path.to_string(),
+            line: at.line,
+            column,
+            message: message.to_string(),
+        }))
+    };
+
+    let key_start = i;
+    if !(line[i].c.is_alphabetic() || line[i].c == '_') {
+        return error(i, "expected a key");
+    }
+    while i < line.len() && (line[i].c.is_alphanumeric() || line[i].c == '_') {
+        i += 1;
+    }
+    let key: String = line[key_start..i].iter().map(|c| c.c).collect();
+
+    let after_key = i;
+    i = skip_ws(line, i);
+    if i == line.len() || line[i].c != '"' {
+        return error(i, "expected a quoted message after the key");
+    }
+    if i == after_key {
+        return error(i, "expected whitespace between the key and the message");
+    }
+    let separator: String = line[after_key..i].iter().map(|c| c.c).collect();
+
+    let first_quote = i;
+    let mut body = String::new();
+    while i < line.len() && line[i].c == '"' {
+        let open = i;
+        i += 1;
+        loop {
+            match line.get(i).map(|c| c.c) {
+                None => {
+                    return greedy_entry(line, key, separator, first_quote)
+                        .or_else(|| error(open, "unterminated message"))
+                }
+                Some('"') => break,
+                Some('\\') if i + 1 < line.len() => {
+                    body.push('\\');
+                    body.push(line[i + 1].c);
+                    i += 2;
+                }
+                Some(c) => {
+                    body.push(c);
+                    i += 1;
+                }
+            }
+        }
+        i = skip_ws(line, i + 1);
+    }
+
+    if i < line.len() && !starts_with(line, i, "//") {
+        return greedy_entry(line, key, separator, first_quote)
+            .or_else(|| error(i, "unexpected text after the message"));
+    }
+
+    Some(Ok(RawEntry {
+        key,
+        literal: format!("\"{}\"", body),
+        line: line[key_start].line,
+        separator,
+        stray_quotes: false,
+    }))
+}
+
+/// Reads the message from the quote at `open` to the last quote of the
+/// line, escaping the quotes in between.
+fn greedy_entry(
+    line: &[Char],
+    key: String,
+    separator: String,
+    open: usize,
+) -> Option<Result<RawEntry, Error>> {
+    let close = line.iter().rposition(|c| !c.c.is_whitespace())?;
+    if close <= open || line[close].c != '"' {
+        return None;
+    }
+
+    let mut body = String::new();
+    let mut i = open + 1;
+    while i < close {
+        match line[i].c {
+            '\\' if i + 1 < close => {
+                body.push('\\');
+                body.push(line[i + 1].c);
+                i += 2;
+                continue;
+            }
+            '"' => body.push_str("\\\""),
+            c => body.push(c),
+        }
+        i += 1;
+    }
+
+    Some(Ok(RawEntry {
+        key,
+        literal: format!("\"{}\"", body),
+        line: line[open].line,
+        separator,
+        stray_quotes: true,
+    }))
+}
+
+fn skip_ws(line: &[Char], mut i: usize) -> usize {
+    while i < line.len() && line[i].c.is_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+fn starts_with(line: &[Char], i: usize, s: &str) -> bool {
+    (i..)
+        .zip(s.chars())
+        .all(|(pos, c)| line.get(pos).map(|l| l.c) == Some(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(text: &str) -> Vec<(String, String, usize)> {
+        let (entries, errors) = parse_mdb(text, "t.mdb");
+        assert!(errors.is_empty(), "{:?}", errors);
+        entries
+            .into_iter()
+            .map(|e| (e.key, e.literal, e.line))
+            .collect()
+    }
+
+    fn errors(text: &str) -> Vec<String> {
+        let (_, errors) = parse_mdb(text, "t.mdb");
+        errors.iter().map(|e| e.to_string()).collect()
+    }
+
+    fn entry(key: &str, literal: &str, line: usize) -> (String, String, usize) {
+        (key.to_string(), literal.to_string(), line)
+    }
+
+    #[test]
+    fn skips_blank_and_comment_lines() {
+        let text = "# header\n\n  // note\nErr1 \"One\" // trailing\n";
+        assert_eq!(entries(text), [entry("Err1", "\"One\"", 4)]);
+    }
+
+    #[test]
+    fn reads_crlf_lines() {
+        let text = "Err1 \"One\"\r\nErr2\t\"Two \\\"x\\\"\"\r\n";
+        assert_eq!(
+            entries(text),
+            [
+                entry("Err1", "\"One\"", 1),
+                entry("Err2", "\"Two \\\"x\\\"\"", 2),
+            ]
+        );
+        let (parsed, _) = parse_mdb(text, "t.mdb");
+        assert_eq!(parsed[1].separator, "\t");
+    }
+
+    #[test]
+    fn joins_adjacent_messages() {
+        assert_eq!(
+            entries("Err1 \"One \" \"two\""),
+            [entry("Err1", "\"One two\"", 1)]
+        );
+    }
+
+    #[test]
+    fn joins_continued_lines() {
+        let text = "Err1 \"One \\\n two\"\nErr2 \"Two\"\r\n";
+        assert_eq!(
+            entries(text),
+            [
+                entry("Err1", "\"One  two\"", 1),
+                entry("Err2", "\"Two\"", 3)
+            ]
+        );
+        let crlf = "Err1 \\\r\n  \"One\"\r\nErr2 \"Two\"";
+        assert_eq!(
+            entries(crlf),
+            [entry("Err1", "\"One\"", 1), entry("Err2", "\"Two\"", 3)]
+        );
+    }
+
+    #[test]
+    fn reports_errors_at_their_physical_position() {
+        assert_eq!(
+            errors("Err1 \\\n  One\n1Err \"x\"\nErr3\"x\"\nErr4 \"x"),
+            [
+                "t.mdb:2:3: expected a quoted message after the key",
+                "t.mdb:3:1: expected a key",
+                "t.mdb:4:5: expected whitespace between the key and the message",
+                "t.mdb:5:6: unterminated message",
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_stray_quotes_up_to_the_last_quote() {
+        let (parsed, errors) = parse_mdb("Err1 \"Say \"hi\" now\"\nErr2 \"ok\"", "t.mdb");
+        assert!(errors.is_empty());
+        assert_eq!(parsed[0].literal, "\"Say \\\"hi\\\" now\"");
+        assert!(parsed[0].stray_quotes);
+        assert!(!parsed[1].stray_quotes);
+    }
+
+    #[test]
+    fn stray_quotes_need_a_closing_quote() {
+        assert_eq!(
+            errors("Err1 \"One\" two"),
+            ["t.mdb:1:12: unexpected text after the message"]
+        );
+    }
+}
@@ -1,61 +1,167 @@
-use rayon::prelude::*;
-use regex::Regex;
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
 
+use super::decisions::DecisionSource;
+use super::encoding::MdbEncoding;
+use super::error::{Error, Result};
+use super::mdb_entry::{Catalog, MdbEntry};
+use super::mdb_grammar::{parse_mdb, RawEntry};
 use super::FCP;
-use crate::tui::AppEvent;
+use crate::tui::{log_list::LogLevel, AppEvent};
+
+/// Which definition of a key defined twice in one mdb file is used.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub enum DuplicatePolicy {
+    #[default]
+    First,
+    Last,
+    /// Leave the key out and report the file as broken.
+    Error,
+}
+
+impl DuplicatePolicy {
+    pub const NAMES: [&'static str; 3] = ["first", "last", "error"];
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "first" => Some(Self::First),
+            "last" => Some(Self::Last),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+/// An mdb file read and parsed, with everything found wrong on the way.
+pub struct MdbFile {
+    pub fcp: FCP,
+    /// Encoding the file was actually decoded with.
+    pub encoding: MdbEncoding,
+    pub catalog: Catalog,
+    /// Entries in file order, duplicates included.
+    pub entries: Vec<RawEntry>,
+    /// Lines that couldn't be loaded, and duplicates under
+    /// [`DuplicatePolicy::Error`].
+    pub errors: Vec<Error>,
+    /// Problems the loader worked around.
+    pub warnings: Vec<Error>,
+}
 
 pub fn get_loggers(
     paths: &[String],
+    encoding: MdbEncoding,
+    duplicates: DuplicatePolicy,
     tx: std::sync::mpsc::Sender<AppEvent>,
-) -> HashMap<FCP, HashMap<String, String>> {
+    decisions: &mut dyn DecisionSource,
+) -> HashMap<FCP, Catalog> {
     let mut loggers = HashMap::new();
 
     for mdb in paths {
         let msg = format!("Loading file {}", mdb);
-        tx.send(AppEvent::Log(msg, crate::tui::log_list::LogLevel::Info))
-            .unwrap();
-        let file_name = std::path::Path::new(&mdb)
-            .file_stem()
-            .unwrap()
-            .to_str()
-            .unwrap();
-
-        let fcp = FCP::from_str(file_name).unwrap();
-        let mdb_file = std::fs::read_to_string(&mdb).unwrap();
-        let codes = get_mdb_codes(&mdb_file);
-        loggers.insert(fcp, codes);
+        tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+
+        let file = match load_mdb(mdb, encoding, duplicates) {
+            Ok(file) => file,
+            Err(e) => {
+                tx.send(AppEvent::Log(e.to_string(), LogLevel::Error))
+                    .unwrap();
+                continue;
+            }
+        };
+
+        if file.encoding != MdbEncoding::Utf8 {
+            let msg = format!("Decoding {} as {}", mdb, file.encoding.to_str());
+            tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+        }
+        for e in &file.warnings {
+            tx.send(AppEvent::Log(e.to_string(), LogLevel::Warn))
+                .unwrap();
+        }
+        for e in &file.errors {
+            tx.send(AppEvent::Log(e.to_string(), LogLevel::Error))
+                .unwrap();
+        }
+        if !file.errors.is_empty() && decisions.skip_file(mdb, &file.errors) {
+            let msg = format!("Skipping {}", mdb);
+            tx.send(AppEvent::Log(msg, LogLevel::Warn)).unwrap();
+            continue;
+        }
+        loggers.insert(file.fcp, file.catalog);
     }
-    return loggers;
+    loggers
 }
 
-fn get_mdb_codes(mdb: &str) -> HashMap<String, String> {
-    let re = Regex::new(r#"(?P<Code>\w+)"#).unwrap();
+pub fn load_mdb(path: &str, encoding: MdbEncoding, duplicates: DuplicatePolicy) -> Result<MdbFile> {
+    let fcp = std::path::Path::new(path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(FCP::from_str)
+        .ok_or_else(|| Error::NotAnMdb {
+            path: path.to_string(),
+        })?;
+    let bytes = std::fs::read(path).map_err(|source| Error::Read {
+        path: path.to_string(),
+        source,
+    })?;
 
-    let codes = Arc::new(RwLock::new(HashMap::new()));
+    let (text, encoding) = encoding.decode(&bytes);
+    let (entries, errors) = parse_mdb(&text, path);
+    let mut file = MdbFile {
+        fcp,
+        encoding,
+        catalog: HashMap::new(),
+        entries,
+        errors,
+        warnings: vec![],
+    };
+    add_entries(&mut file, path, duplicates);
+    Ok(file)
+}
 
-    mdb.par_lines().for_each(|line| {
-        let cap = re.captures(line);
-        if cap.is_some() {
-            let cap = cap.unwrap();
-            let mdb = get_mdb(line);
-            let code = &cap["Code"];
+/// Fills the catalog of `file` from its entries.
+fn add_entries(file: &mut MdbFile, path: &str, duplicates: DuplicatePolicy) {
+    let mut duplicated = vec![];
 
-            let codes = Arc::clone(&codes);
-            let mut codes = codes.write().expect("RwLock Poisoned");
-            codes.insert(code.to_string(), mdb);
+    for raw in &file.entries {
+        if raw.stray_quotes {
+            file.warnings.push(Error::StrayQuotes {
+                path: path.to_string(),
+                line: raw.line,
+                key: raw.key.clone(),
+            });
         }
-    });
+        let entry = MdbEntry::new(&raw.key, &raw.literal, path, raw.line);
 
-    let codes = codes.read().unwrap().clone();
-    codes
-}
-
-fn get_mdb(mdb: &str) -> String {
-    let re = Regex::new(r#"\w+\s+(?P<mdb>\".+\")"#).unwrap();
-    let cap = re.captures(mdb).unwrap();
-    let mdb_result = cap["mdb"].to_string();
+        let Some(first) = file.catalog.get(&entry.key) else {
+            file.catalog.insert(entry.key.clone(), entry);
+            continue;
+        };
+        let kept = match duplicates {
+            DuplicatePolicy::First => Some(first.line),
+            DuplicatePolicy::Last => Some(entry.line),
+            DuplicatePolicy::Error => None,
+        };
+        let e = Error::DuplicateKey {
+            path: path.to_string(),
+            key: entry.key.clone(),
+            first_line: first.line,
+            line: entry.line,
+            kept,
+        };
+        match duplicates {
+            DuplicatePolicy::First => file.warnings.push(e),
+            DuplicatePolicy::Last => {
+                file.warnings.push(e);
+                file.catalog.insert(entry.key.clone(), entry);
+            }
+            DuplicatePolicy::Error => {
+                duplicated.push(entry.key);
+                file.errors.push(e);
+            }
+        }
+    }
 
-    mdb_result
+    for key in duplicated {
+        file.catalog.remove(&key);
+    }
 }
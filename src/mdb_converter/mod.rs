@@ -1,7 +1,25 @@
+pub mod args;
+pub mod converted;
+pub mod decisions;
+pub mod diff;
+pub mod discover;
+pub mod encoding;
+pub mod error;
+pub mod export;
+pub mod infer;
+pub mod lexer;
+pub mod lint;
+pub mod mdb_entry;
+pub mod mdb_grammar;
 pub mod mdb_parser;
+pub mod output;
 pub mod parser;
+pub mod recogniser;
+pub mod report;
+pub mod statement;
+pub mod template;
 
-#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone, Debug)]
 pub enum FCP {
     SE,
     ASM,
@@ -21,6 +39,7 @@ pub enum FCP {
 }
 
 impl FCP {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "fcpasm" => Some(Self::ASM),
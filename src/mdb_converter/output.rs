@@ -0,0 +1,204 @@
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub enum OutputFormat {
+    /// Converted files.
+    #[default]
+    Out,
+    /// Unified diff per file.
+    Diff,
+    /// One unified diff for all files, `conversion.patch`.
+    Patch,
+}
+
+impl OutputFormat {
+    pub const NAMES: [&'static str; 3] = ["out", "diff", "patch"];
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "out" => Some(Self::Out),
+            "diff" => Some(Self::Diff),
+            "patch" => Some(Self::Patch),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Out => "out",
+            Self::Diff => "diff",
+            Self::Patch => "patch",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum Backup {
+    #[default]
+    None,
+    /// Copy next to the original with this suffix appended.
+    Suffix(String),
+    /// Copy into this directory, mirroring the source tree.
+    Dir(String),
+}
+
+/// Where and how converted files end up.
+#[derive(Clone, Debug)]
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    pub dir: String,
+    /// Overwrite the .cpp files themselves, only with [`OutputFormat::Out`].
+    pub in_place: bool,
+    pub backup: Backup,
+    /// Recreate the directories of the input paths under `dir`.
+    pub mirror: bool,
+    /// Write converted files under their own name instead of `<name>.out`.
+    pub keep_names: bool,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::default(),
+            dir: "output".to_string(),
+            in_place: false,
+            backup: Backup::None,
+            mirror: false,
+            keep_names: false,
+        }
+    }
+}
+
+impl OutputOptions {
+    /// Path the output for `file_name` is written to.
+    pub fn target(&self, file_name: &str) -> PathBuf {
+        if self.in_place && self.format == OutputFormat::Out {
+            return PathBuf::from(file_name);
+        }
+
+        let path = Path::new(file_name);
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        if !(self.keep_names && self.format == OutputFormat::Out) {
+            // `Foo.cpp.out` and `Foo.h.out` stay apart
+            name.push(format!(".{}", self.format.extension()));
+        }
+
+        let mut target = PathBuf::from(&self.dir);
+        if self.mirror {
+            if let Some(parent) = path.parent() {
+                target.push(relative(parent));
+            }
+        }
+        target.push(name);
+        target
+    }
+
+    /// Writes the converted contents of `file_name`, backing the original
+    /// up first when converting in place.
+    pub fn write_converted(&self, file_name: &str, contents: &str) -> anyhow::Result<PathBuf> {
+        let target = self.target(file_name);
+        if self.in_place {
+            self.backup(file_name)?;
+        }
+        write(&target, contents)?;
+        Ok(target)
+    }
+
+    pub fn write_diff(&self, file_name: &str, diff: &str) -> anyhow::Result<PathBuf> {
+        let target = self.target(file_name);
+        write(&target, diff)?;
+        Ok(target)
+    }
+
+    pub fn write_patch(&self, patch: &str) -> anyhow::Result<PathBuf> {
+        let target = Path::new(&self.dir).join("conversion.patch");
+        write(&target, patch)?;
+        Ok(target)
+    }
+
+    /// Writes a file generated alongside the converted ones.
+    pub fn write_companion(&self, name: &str, contents: &str) -> anyhow::Result<PathBuf> {
+        let target = Path::new(&self.dir).join(name);
+        write(&target, contents)?;
+        Ok(target)
+    }
+
+    fn backup(&self, file_name: &str) -> anyhow::Result<()> {
+        let backup = match &self.backup {
+            Backup::None => return Ok(()),
+            Backup::Suffix(suffix) => PathBuf::from(format!("{file_name}{suffix}")),
+            Backup::Dir(dir) => Path::new(dir).join(relative(Path::new(file_name))),
+        };
+        if let Some(parent) = backup.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(file_name, backup)?;
+        Ok(())
+    }
+}
+
+/// `path` made safe to append to another directory: no root and no `..`.
+fn relative(path: &Path) -> PathBuf {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_os_string()),
+            Component::ParentDir => Some("__".into()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn write(target: &Path, contents: &str) -> anyhow::Result<()> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(target, contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(options: OutputOptions, file_name: &str) -> String {
+        options.target(file_name).to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn targets_keep_the_source_extension() {
+        let options = OutputOptions::default();
+        assert_eq!(
+            target(options.clone(), "src/a/Foo.cpp"),
+            "output/Foo.cpp.out"
+        );
+        assert_eq!(target(options.clone(), "src/a/Foo.h"), "output/Foo.h.out");
+        let diff = OutputOptions {
+            format: OutputFormat::Diff,
+            ..options
+        };
+        assert_eq!(target(diff, "Foo.cpp"), "output/Foo.cpp.diff");
+    }
+
+    #[test]
+    fn targets_mirror_and_keep_names() {
+        let options = OutputOptions {
+            mirror: true,
+            keep_names: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            target(options.clone(), "./src/a/Foo.cpp"),
+            "output/src/a/Foo.cpp"
+        );
+        assert_eq!(
+            target(options.clone(), "../x/Foo.cpp"),
+            "output/__/x/Foo.cpp"
+        );
+        let in_place = OutputOptions {
+            in_place: true,
+            ..options
+        };
+        assert_eq!(target(in_place, "src/a/Foo.cpp"), "src/a/Foo.cpp");
+    }
+}
@@ -2,216 +2,509 @@ use colored::*;
 use regex::Regex;
 
 use std::collections::HashMap;
-
+use std::path::PathBuf;
+
+use super::args::{adapt_args, check_args, ArgTypes};
+use super::converted::ConvertedForms;
+use super::decisions::DecisionSource;
+use super::diff::{patch_path, unified_diff, Change, Rewrite};
+use super::discover::{discover_loggers, DEFAULT_LOGGER_PATTERNS};
+use super::encoding::escape_non_ascii;
+use super::error::{Error, Result};
+use super::export::{cpp_fcp_name, sorted_catalogs, ReferenceMode, TranslateMode, REFERENCE_FILE};
+use super::infer::infer_fcp;
+use super::lexer::{tokenize, TokenKind};
+use super::mdb_entry::{Catalog, MdbEntry};
+use super::output::{OutputFormat, OutputOptions};
+pub use super::recogniser::{Recogniser, Recognisers};
+use super::report::Report;
+use super::template::{render, Templates};
 use super::FCP;
 use crate::tui::{log_list::LogLevel, AppEvent};
 
 pub fn parser(
     cli: crate::cli::Args,
     tx: std::sync::mpsc::Sender<AppEvent>,
-    app2parser_receiver: std::sync::mpsc::Receiver<AppEvent>,
-) {
+    decisions: &mut dyn DecisionSource,
+) -> Report {
     let mdb_files = cli.mdb_files;
     let cpp_files = cli.cpp_files;
     //let mdb_files = vec!["mdb/fcpasm.mdb".to_string(), "mdb/fcpse.mdb".to_string()];
     //let cpp_files = vec!["cpp/FcpAsm.cpp".to_string()];
 
-    let loggers = super::mdb_parser::get_loggers(&mdb_files, tx.clone());
+    let loggers = super::mdb_parser::get_loggers(
+        &mdb_files,
+        cli.mdb_encoding,
+        cli.duplicates,
+        tx.clone(),
+        decisions,
+    );
+    let mut report = Report::default();
+
+    let types = match &cli.types {
+        None => ArgTypes::default(),
+        Some(path) => ArgTypes::load(path).unwrap_or_else(|e| {
+            let msg = format!("Couldn't load types from {}: {}", path, e);
+            tx.send(AppEvent::Log(msg, LogLevel::Error)).unwrap();
+            ArgTypes::default()
+        }),
+    };
+
+    let templates = match &cli.templates {
+        None => Templates::default(),
+        Some(path) => Templates::load(path).unwrap_or_else(|e| {
+            let msg = format!("Couldn't load templates from {}: {}", path, e);
+            tx.send(AppEvent::Log(msg, LogLevel::Error)).unwrap();
+            Templates::default()
+        }),
+    };
+
+    let recognisers = match &cli.recognisers {
+        None => Recognisers::default(),
+        Some(path) => Recognisers::load(path).unwrap_or_else(|e| {
+            let msg = format!("Couldn't load recognisers from {}: {}", path, e);
+            tx.send(AppEvent::Log(msg, LogLevel::Error)).unwrap();
+            Recognisers::default()
+        }),
+    };
+
+    let converted = ConvertedForms::new(&templates, &loggers);
+
+    let mut patch = String::new();
+    // output path -> file it was written for
+    let mut targets = HashMap::new();
+    // diffs name files relative to where they are applied from
+    let cwd = std::env::current_dir().unwrap_or_default();
+
+    let logger_patterns: Vec<Regex> = DEFAULT_LOGGER_PATTERNS
+        .iter()
+        .map(|p| p.to_string())
+        .chain(cli.logger_patterns.iter().cloned())
+        .filter_map(|p| Regex::new(&p).ok())
+        .collect();
 
     for file_name in cpp_files {
         let str = format!("------------ Editing {file_name} -------------");
         tx.send(AppEvent::Log(str, LogLevel::Info)).unwrap();
 
+        let buffer = match std::fs::read_to_string(&file_name) {
+            Ok(buffer) => buffer,
+            Err(source) => {
+                let e = Error::Read {
+                    path: file_name.clone(),
+                    source,
+                };
+                tx.send(AppEvent::Log(e.to_string(), LogLevel::Error))
+                    .unwrap();
+                continue;
+            }
+        };
+
+        let inferred = if cli.infer {
+            infer_file_fcp(&file_name, &buffer, &loggers, &tx)
+        } else {
+            None
+        };
+
         let mut logger_map = HashMap::new();
 
-        loggers.iter().for_each(|(logger, _)| {
-            let msg = format!(
-                "Specify variable name for logger {:?} (for auto-search based on comments)",
-                logger
-            );
-            tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
-            tx.send(AppEvent::WaitForInput).unwrap();
-            let mut name = String::new();
-            for event in &app2parser_receiver {
-                match event {
-                    AppEvent::Command(n) => {
-                        name = n.trim().to_string();
-                        tx.send(AppEvent::Log(name.clone(), LogLevel::Info))
-                            .unwrap();
-                        break;
+        let discovered = discover_loggers(&buffer, &logger_patterns, &sorted_fcps(&loggers));
+        if !discovered.is_empty() && decisions.confirm_loggers(&file_name, &discovered) {
+            for (name, fcp) in discovered {
+                let msg = format!("Using logger {} for {}", name, fcp.to_str());
+                tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+                logger_map.insert(name, fcp);
+            }
+        } else if inferred.is_some() {
+            // names given earlier still find codes their comments point at
+            for fcp in sorted_fcps(&loggers) {
+                if let Some(name) = decisions.known_logger_name(fcp) {
+                    logger_map.insert(name, fcp);
+                }
+            }
+        } else {
+            for fcp in sorted_fcps(&loggers) {
+                match decisions.logger_name(fcp) {
+                    Some(name) => {
+                        logger_map.insert(name, fcp);
+                    }
+                    None => {
+                        let msg = format!("No variable name for logger {:?}", fcp);
+                        tx.send(AppEvent::Log(msg, LogLevel::Warn)).unwrap();
                     }
-                    _ => (),
                 }
             }
-            logger_map.insert(name, *logger);
-        });
+        }
 
-        let buffer = std::fs::read_to_string(&file_name).unwrap();
-        let str = format!("-------------- Removing multi-line logs --------------");
-        tx.send(AppEvent::Log(str, LogLevel::Info)).unwrap();
-        let buffer = join_log_lines(&buffer, tx.clone());
         tx.send(AppEvent::NewFile(buffer.clone())).unwrap();
-        let str = format!("-------------- Replacing logs --------------");
+        let str = "-------------- Replacing logs --------------".to_string();
         tx.send(AppEvent::Log(str, LogLevel::Info)).unwrap();
 
-        let mut res = String::new();
-        let mut line_num = 0;
-        for line in buffer.lines() {
-            line_num += 1;
-            if line.trim().is_empty() {
-                continue;
+        let types = types.for_file(&buffer);
+        let ctx = FileContext {
+            file_name: &file_name,
+            buffer: &buffer,
+            loggers: &loggers,
+            logger_map: &logger_map,
+            escape_non_ascii: cli.escape_non_ascii,
+            types: &types,
+            translate: cli.translate,
+            templates: &templates,
+            converted: &converted,
+            reference: cli.reference,
+            inferred,
+        };
+
+        let mut rewrite = Rewrite::new(&buffer);
+        let mut skipped = false;
+        for (recogniser, span) in recognisers.find_statements(&buffer) {
+            let statement = &buffer[span.start..span.end];
+            if span.first_line != span.last_line {
+                let msg = format!(
+                    "------ Multiple-line {} on lines {}-{} ------\n{}",
+                    recogniser.name(),
+                    span.first_line,
+                    span.last_line,
+                    statement.yellow()
+                );
+                tx.send(AppEvent::Log(msg, LogLevel::Trace)).unwrap();
             }
-            let new_line = parse_line(
-                line,
-                &loggers,
-                &logger_map,
-                &buffer,
-                line_num,
+
+            let parsed = parse_line(
+                statement,
+                recogniser,
+                span.first_line,
+                &ctx,
                 tx.clone(),
-                &app2parser_receiver,
+                decisions,
+                &mut report,
             );
-            if new_line.is_none() {
-                if res.len() > 0 {
-                    res.push('\n');
+            match parsed {
+                Err(e) => {
+                    let msg = format!("{}:{}: {}", file_name, span.first_line, e);
+                    tx.send(AppEvent::Log(msg, LogLevel::Error)).unwrap();
+                    if decisions.skip_file(&file_name, &[e]) {
+                        skipped = true;
+                        break;
+                    }
                 }
-                res.push_str(line);
-            } else {
-                if res.len() > 0 {
-                    res.push('\n');
+                Ok(None) => (),
+                Ok(Some(new_statement)) => {
+                    let change = rewrite.replace(span.start, span.end, &new_statement);
+                    show_change(&tx, change);
                 }
-                let new_line = new_line.unwrap();
-                res.push_str(&new_line);
-                tx.send(AppEvent::ReplaceFileLine(line_num, new_line))
-                    .unwrap();
             }
         }
+        let (res, changes) = rewrite.finish();
+
+        if skipped {
+            let msg = format!("Skipping {}", file_name);
+            tx.send(AppEvent::Log(msg, LogLevel::Warn)).unwrap();
+            continue;
+        }
+
+        let written = match cli.output.format {
+            // leave untouched files and their backups alone
+            OutputFormat::Out if cli.output.in_place && changes.is_empty() => {
+                let msg = format!("Nothing to convert in {}", file_name);
+                tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+                None
+            }
+            OutputFormat::Out => Some(
+                claim_target(&mut targets, &cli.output, &file_name)
+                    .and_then(|_| cli.output.write_converted(&file_name, &res)),
+            ),
+            OutputFormat::Diff | OutputFormat::Patch if changes.is_empty() => None,
+            OutputFormat::Diff | OutputFormat::Patch => match patch_path(&file_name, &cwd) {
+                Err(e) => {
+                    tx.send(AppEvent::Log(e.to_string(), LogLevel::Error))
+                        .unwrap();
+                    None
+                }
+                Ok(path) => {
+                    let diff = unified_diff(&path, &buffer, &changes);
+                    if cli.output.format == OutputFormat::Diff {
+                        Some(
+                            claim_target(&mut targets, &cli.output, &file_name)
+                                .and_then(|_| cli.output.write_diff(&file_name, &diff)),
+                        )
+                    } else {
+                        patch.push_str(&diff);
+                        None
+                    }
+                }
+            },
+        };
+        log_written(&tx, written);
+    }
+
+    if cli.output.format == OutputFormat::Patch {
+        log_written(&tx, Some(cli.output.write_patch(&patch)));
+    }
+
+    if let Some(reference) = cli.reference {
+        let catalogs = sorted_catalogs(&loggers);
+        let literal = |l: &str| {
+            if cli.escape_non_ascii {
+                escape_non_ascii(l)
+            } else {
+                l.to_string()
+            }
+        };
+        let header = reference.header(&catalogs);
+        let source = reference.source(&catalogs, literal);
+        for (ext, text) in [("h", header), ("cpp", source)] {
+            let name = format!("{}.{}", REFERENCE_FILE, ext);
+            log_written(&tx, Some(cli.output.write_companion(&name, &text)));
+        }
+        let msg = format!("Converted files need #include \"{}.h\"", REFERENCE_FILE);
+        tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+    }
 
-        if !std::path::Path::new("output").exists() {
-            std::fs::create_dir("output").expect("Couldn't create output directory!");
+    if !report.is_empty() {
+        let str = "-------------- Lines to review --------------".to_string();
+        tx.send(AppEvent::Log(str, LogLevel::Info)).unwrap();
+        for line in report.to_string().lines() {
+            tx.send(AppEvent::Log(line.to_string(), LogLevel::Warn))
+                .unwrap();
         }
-        let path = std::path::Path::new(&file_name);
-        let file_stem = path
-            .file_stem()
-            .expect(&format!("Couldn't take file stem for file {}", file_name))
-            .to_str()
-            .expect("Path contains non-valid UTF-8");
-        std::fs::write(&format!("output/{}.out", file_stem), res)
-            .expect("Couldn't write output file");
-
-        tx.send(AppEvent::ReadyToQuit).unwrap();
     }
+    tx.send(AppEvent::ReadyToQuit).unwrap();
+
+    report
 }
 
-pub fn join_log_lines(buffer: &String, tx: std::sync::mpsc::Sender<AppEvent>) -> String {
-    let mut res = String::new();
+/// Shows the rewritten lines of `change` in place of the original ones.
+fn show_change(tx: &std::sync::mpsc::Sender<AppEvent>, change: &Change) {
+    let count = change.last_line - change.first_line + 1;
+    let mut lines: Vec<String> = change.new_text.lines().map(String::from).collect();
+    if lines.len() > count {
+        let rest = lines.split_off(count - 1).join("\n");
+        lines.push(rest);
+    }
+    lines.resize(count, String::new());
+    for (n, line) in (change.first_line..).zip(lines) {
+        tx.send(AppEvent::ReplaceFileLine(n, line)).unwrap();
+    }
+}
 
-    let mut split_lines = String::new();
-    let mut joined = true;
+/// Fails when the output for `file_name` would overwrite the one written
+/// for another file, remembering its target otherwise.
+fn claim_target(
+    targets: &mut HashMap<PathBuf, String>,
+    output: &OutputOptions,
+    file_name: &str,
+) -> anyhow::Result<()> {
+    let target = output.target(file_name);
+    if let Some(first) = targets.get(&target) {
+        anyhow::bail!(
+            "{} would overwrite {}, written for {}",
+            file_name,
+            target.display(),
+            first
+        );
+    }
+    targets.insert(target, file_name.to_string());
+    Ok(())
+}
 
-    let mut curr_line_num = 0;
-    let mut new_line_num = 0;
+fn log_written(
+    tx: &std::sync::mpsc::Sender<AppEvent>,
+    written: Option<anyhow::Result<std::path::PathBuf>>,
+) {
+    let (msg, level) = match written {
+        None => return,
+        Some(Ok(path)) => (format!("Wrote {}", path.display()), LogLevel::Info),
+        Some(Err(e)) => (format!("Couldn't write output: {}", e), LogLevel::Error),
+    };
+    tx.send(AppEvent::Log(msg, level)).unwrap();
+}
 
-    for line in buffer.lines() {
-        curr_line_num += 1;
-        if !joined {
-            split_lines.push_str(&format!(" {}", line.trim()));
+/// Everything `parse_line` needs to know about the file being converted.
+pub struct FileContext<'a> {
+    pub file_name: &'a str,
+    pub buffer: &'a str,
+    pub loggers: &'a HashMap<FCP, Catalog>,
+    pub logger_map: &'a HashMap<String, FCP>,
+    pub escape_non_ascii: bool,
+    pub types: &'a ArgTypes,
+    pub templates: &'a Templates,
+    /// Statements an earlier run already rewrote are left as they are.
+    pub converted: &'a ConvertedForms,
+    /// Refers to the catalog instead of inlining messages when set.
+    pub reference: Option<ReferenceMode>,
+    /// Wraps messages in a Qt translation call when set.
+    pub translate: Option<TranslateMode>,
+    /// Mdb file used for lines without comments when its catalog has the code.
+    pub inferred: Option<FCP>,
+}
 
-            tx.send(AppEvent::Log(line.to_string(), LogLevel::Trace))
-                .unwrap();
+fn infer_file_fcp(
+    file_name: &str,
+    buffer: &str,
+    loggers: &HashMap<FCP, Catalog>,
+    tx: &std::sync::mpsc::Sender<AppEvent>,
+) -> Option<FCP> {
+    let inference = infer_fcp(file_name, buffer, &sorted_fcps(loggers));
+    for (fcp, score, reasons) in &inference.scores {
+        let msg = format!("{} scored {} ({})", fcp.to_str(), score, reasons.join(", "));
+        tx.send(AppEvent::Log(msg, LogLevel::Trace)).unwrap();
+    }
 
-            if line.ends_with(";") {
-                res.push_str(&split_lines);
-                res.push('\n');
-                let msg = format!(
-                    "------ Above is replaced with (now on line {})------\n{}",
-                    new_line_num, split_lines
-                );
-                tx.send(AppEvent::Log(msg.to_string(), LogLevel::Trace))
-                    .unwrap();
-                split_lines.clear();
-                joined = true;
-            }
-        } else {
-            new_line_num += 1;
-            let re = Regex::new("(qCritical|qInfo|qWarning)").unwrap();
-            let cap = re.captures(line);
-            if cap.is_none() {
-                res.push_str(line);
-                res.push('\n');
-                continue;
-            }
+    let inferred = inference.confident();
+    let msg = match inferred {
+        Some(fcp) => format!("Inferred {} for {}", fcp.to_str(), file_name),
+        None => format!("Couldn't infer the mdb file for {}", file_name),
+    };
+    tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+    inferred
+}
 
-            if !line.ends_with(";") {
-                joined = false;
-                split_lines.push_str(line);
-                let msg = format!(
-                    "------ Multiple-line logs starting on line {} (now on line {}) ------\n{}",
-                    curr_line_num,
-                    new_line_num,
-                    line.yellow()
-                );
-                tx.send(AppEvent::Log(msg.to_string(), LogLevel::Info))
-                    .unwrap();
-                continue;
-            }
+fn sorted_fcps(loggers: &HashMap<FCP, Catalog>) -> Vec<FCP> {
+    let mut fcps: Vec<FCP> = loggers.keys().copied().collect();
+    fcps.sort();
+    fcps
+}
 
-            let msg = format!(
-                "------ One-line log on line {} (now on line {}) ------\n{}",
-                curr_line_num, new_line_num, line
-            );
-            tx.send(AppEvent::Log(msg.to_string(), LogLevel::Trace))
-                .unwrap();
+/// Mdb entry for `err` from the catalogs that have it, asking `decisions`
+/// when there are several. `None` once the line is reported as unknown or
+/// unresolved.
+fn select_mdb<'a>(
+    line: &str,
+    line_num: usize,
+    err: &str,
+    ctx: &FileContext<'a>,
+    tx: &std::sync::mpsc::Sender<AppEvent>,
+    decisions: &mut dyn DecisionSource,
+    report: &mut Report,
+) -> Result<Option<(FCP, &'a MdbEntry)>> {
+    let mut mdb_match = None;
+    let candidates: Vec<FCP> = sorted_fcps(ctx.loggers)
+        .into_iter()
+        .filter(|fcp| ctx.loggers[fcp].contains_key(err))
+        .collect();
+
+    if candidates.is_empty() {
+        let msg = format!("Unknown error code {err} on line {line_num}");
+        tx.send(AppEvent::Log(msg, LogLevel::Warn)).unwrap();
+        report.unknown(
+            ctx.file_name,
+            line_num,
+            err,
+            "code is in none of the mdb files",
+        );
+        return Ok(None);
+    }
 
-            res.push_str(line);
-            res.push('\n');
-        }
+    if let [fcp] = candidates[..] {
+        let mdb = &ctx.loggers[&fcp][err];
+        mdb_match = Some((fcp, mdb));
+        let msg = format!(
+            "Got {} for {} code, only in {}",
+            mdb.literal,
+            err,
+            fcp.to_str()
+        );
+        tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
     }
 
-    return res;
+    while mdb_match.is_none() {
+        let fcp = match decisions.select_fcp(ctx.file_name, line_num, line, err, &candidates) {
+            Some(fcp) => fcp,
+            None => {
+                let msg = format!("No mdb file selected for {err} on line {line_num}");
+                tx.send(AppEvent::Log(msg, LogLevel::Warn)).unwrap();
+                report.unresolved(ctx.file_name, line_num, err, "no mdb file selected");
+                return Ok(None);
+            }
+        };
+        let codes = ctx.loggers.get(&fcp).ok_or(Error::MissingMdb { fcp })?;
+
+        match codes.get(err) {
+            None => {
+                let msg = format!("No error code for {err} in {}", fcp.to_str());
+                tx.send(AppEvent::Log(msg, LogLevel::Error)).unwrap();
+                if !decisions.interactive() {
+                    let reason = format!("no error code in {}", fcp.to_str());
+                    report.unresolved(ctx.file_name, line_num, err, &reason);
+                    return Ok(None);
+                }
+            }
+            Some(mdb) => {
+                mdb_match = Some((fcp, mdb));
+                let msg = format!("Got {} for {} code in {}", mdb.literal, err, fcp.to_str());
+                tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+            }
+        }
+    }
+    Ok(mdb_match)
 }
 
 pub fn parse_line(
     line: &str,
-    loggers: &HashMap<FCP, HashMap<String, String>>,
-    logger_map: &HashMap<String, FCP>,
-    file: &str,
+    recogniser: &dyn Recogniser,
     line_num: usize,
+    ctx: &FileContext,
     tx: std::sync::mpsc::Sender<AppEvent>,
-    app2parser_receiver: &std::sync::mpsc::Receiver<AppEvent>,
-) -> Option<String> {
-    let re = Regex::new("(qCritical|qInfo|qWarning)").unwrap();
-    let cap = re.captures(line);
-    if cap.is_none() {
-        return None;
+    decisions: &mut dyn DecisionSource,
+    report: &mut Report,
+) -> Result<Option<String>> {
+    if let Some(entries) = ctx.converted.find(line) {
+        let (code, reason) = match entries {
+            [(fcp, key)] => (
+                key.clone(),
+                format!("message of {} already in place", fcp.to_str()),
+            ),
+            entries => {
+                let names: Vec<String> = entries
+                    .iter()
+                    .map(|(fcp, key)| format!("{} in {}", key, fcp.to_str()))
+                    .collect();
+                let reason = format!("message shared by {} already in place", names.join(", "));
+                ("ambiguous".to_string(), reason)
+            }
+        };
+        let msg = format!("Line {line_num}: {reason}, leaving it");
+        tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+        report.converted(ctx.file_name, line_num, &code, &reason);
+        return Ok(None);
     }
 
-    let re = Regex::new(r#"\"\s*(?P<Err>\w+)+\s*\"\s*(?P<Strings>(?:<<.+)*);"#).unwrap();
-    let cap = re.captures(line);
-    let cap = match cap {
-        None => return None,
-        Some(_) => cap.unwrap(),
+    let Some(stmt) = recogniser.parse(line) else {
+        return Ok(None);
     };
-    let err: &str = &cap["Err"];
-    let err = err.trim();
-    let strings = &cap["Strings"].to_string();
-    let strings = strings.replace("<", "\n");
-
-    let mut strings_vec = vec![];
-    for string in strings.lines() {
-        if !string.trim().is_empty() {
-            strings_vec.push(string.trim().to_string());
-        }
+    let err = stmt.code.as_str();
+    let strings_vec = stmt.args.clone();
+
+    if tokenize(line).iter().any(|t| t.kind == TokenKind::Comment) {
+        let msg = format!("Line {line_num}: comments inside the statement, leaving it");
+        tx.send(AppEvent::Log(msg, LogLevel::Warn)).unwrap();
+        report.unresolved(
+            ctx.file_name,
+            line_num,
+            err,
+            "comments inside the statement would be lost",
+        );
+        return Ok(None);
     }
-    // let re = Regex::new(r#"(\S+)"#).unwrap();
-    // for (_, [string]) in re.captures_iter(&strings).map(|c| c.extract()) {
-    //     strings_vec.push(string.trim().to_string());
-    // }
 
-    let mut commented_lines = find_comment_around_line(file, line_num as usize);
+    let mut commented_lines = find_comment_around_line(ctx.buffer, line_num);
     if !commented_lines.contains(err) {
         commented_lines.clear();
     }
-    let mut mdb_match = String::new();
+    let mut mdb_match = None;
+    let inferred = ctx
+        .inferred
+        .and_then(|fcp| Some((fcp, ctx.loggers.get(&fcp)?.get(err)?)));
+    let log_inferred = |fcp: FCP, mdb: &MdbEntry| {
+        let msg = format!(
+            "Got {} for {} code in inferred {}",
+            mdb.literal,
+            err,
+            fcp.to_str()
+        );
+        tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+    };
 
     if !commented_lines.is_empty() {
         let msg = format!(
@@ -222,103 +515,142 @@ pub fn parse_line(
 
         tx.send(AppEvent::Log(msg, LogLevel::Trace)).unwrap();
 
-        logger_map.iter().for_each(|(k, fcp)| {
-            if !k.is_empty() {
-                if commented_lines.contains(k) {
-                    let codes = loggers.get(fcp).unwrap();
-                    let opt = codes.get(err);
-
-                    if opt.is_none() {
+        for (k, fcp) in ctx.logger_map {
+            if !k.is_empty() && commented_lines.contains(k) {
+                let codes = ctx
+                    .loggers
+                    .get(fcp)
+                    .ok_or(Error::MissingMdb { fcp: *fcp })?;
+                match codes.get(err) {
+                    None => {
                         let msg = format!("No error code for {err} in {k}");
                         tx.send(AppEvent::Log(msg, LogLevel::Error)).unwrap();
-                    } else {
-                        mdb_match = opt.unwrap().to_string();
-                        let msg = format!("Got {} for {} code in {}", mdb_match, err, k);
+                    }
+                    Some(mdb) => {
+                        mdb_match = Some((*fcp, mdb));
+                        let msg = format!("Got {} for {} code in {}", mdb.literal, err, k);
                         tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
                     }
                 }
             }
-        })
-    } else {
-        let mut base_str = format!(
-            "---------For line {line_num} select mdb file----------\n{}\n",
-            line.trim()
-        );
+        }
+        if mdb_match.is_none() {
+            let msg = format!("No logger in comments around line {line_num} has {err}");
+            tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
+        }
+    }
+    if let (None, Some((fcp, mdb))) = (mdb_match, inferred) {
+        log_inferred(fcp, mdb);
+        mdb_match = inferred;
+    }
 
-        let mut count = 0;
-        let mut fcp_vec = vec![];
-        logger_map.iter().for_each(|(_, fcp)| {
-            count += 1;
-            fcp_vec.push(*fcp);
-            base_str.push_str(&format!("{} - {}\n", count, fcp.to_str()));
-        });
-
-        tx.send(AppEvent::Log(base_str, LogLevel::Info)).unwrap();
-        tx.send(AppEvent::JumpLine(line_num)).unwrap();
-
-        loop {
-            tx.send(AppEvent::WaitForInput).unwrap();
-            let mut name = String::new();
-            for event in app2parser_receiver {
-                match event {
-                    AppEvent::Command(n) => {
-                        name = n.trim().to_string();
-                        tx.send(AppEvent::Log(name.clone(), LogLevel::Info))
-                            .unwrap();
-                        break;
-                    }
-                    _ => (),
-                }
-            }
+    let (fcp, entry) = match mdb_match {
+        Some(found) => found,
+        None => match select_mdb(line, line_num, err, ctx, &tx, decisions, report)? {
+            Some(found) => found,
+            None => return Ok(None),
+        },
+    };
 
-            match name.trim().parse::<usize>() {
-                Ok(num) => {
-                    if num > fcp_vec.len() {
-                        let msg = format!("wrong input! try again");
-                        tx.send(AppEvent::Log(msg, LogLevel::Error)).unwrap();
-                        continue;
-                    } else {
-                        let fcp = fcp_vec[num - 1];
-                        let codes = loggers.get(&fcp).unwrap();
-                        let opt = codes.get(err);
-
-                        if opt.is_none() {
-                            let msg = format!("No error code for {err} in {}", fcp.to_str());
-                            tx.send(AppEvent::Log(msg, LogLevel::Error)).unwrap();
-                        } else {
-                            mdb_match = opt.unwrap().to_string();
-                            let msg =
-                                format!("Got {} for {} code in {}", mdb_match, err, fcp.to_str());
-                            tx.send(AppEvent::Log(msg, LogLevel::Info)).unwrap();
-                            break;
-                        }
-                    }
-                }
-                Err(..) => {
-                    let msg = format!("wrong input! try again");
-                    tx.send(AppEvent::Log(msg, LogLevel::Error)).unwrap();
-                }
-            }
+    let problems = check_args(entry, &strings_vec);
+    if !problems.is_empty() {
+        for problem in &problems {
+            let msg = format!("Line {line_num}: {problem}");
+            tx.send(AppEvent::Log(msg, LogLevel::Error)).unwrap();
+            report.mismatch(ctx.file_name, line_num, err, problem);
         }
+        return Ok(None);
     }
 
-    let mut new_line = format!("QString::asprintf({}", mdb_match);
-    for string in strings_vec {
-        new_line = format!("{}, {}", new_line, string);
+    let (strings_vec, unchecked) = adapt_args(entry, &strings_vec, ctx.types);
+    for arg in &unchecked {
+        let msg = format!("Line {line_num}: couldn't infer the type of %s argument {arg}");
+        tx.send(AppEvent::Log(msg, LogLevel::Warn)).unwrap();
+        let reason = format!("type of %s argument {arg} not inferred");
+        report.unchecked(ctx.file_name, line_num, err, &reason);
     }
-    new_line.push_str(") << ENDL;");
-
-    if line.contains("qCritical") {
-        new_line = format!("qCritical() << {}", new_line);
-    } else if line.contains("qInfo") {
-        new_line = format!("qInfo() << {}", new_line);
-    } else if line.contains("qWarning") {
-        new_line = format!("qWarning() << {}", new_line);
+
+    let mut mdb_match = match ctx.reference {
+        Some(reference) => reference.reference(fcp, err),
+        None if ctx.escape_non_ascii => escape_non_ascii(&entry.literal),
+        None => entry.literal.clone(),
     };
-    let re = Regex::new(r#"(?P<spaces>.*)(?:qCritical|qInfo|qWarning)"#).unwrap();
-    let caps = re.captures(line).unwrap();
-    let spaces = &caps["spaces"];
-    new_line = format!("{}{}", spaces, new_line);
+    if let Some(translate) = ctx.translate {
+        mdb_match = format!(
+            "{}.toUtf8().constData()",
+            translate.wrap(fcp, &mdb_match, err)
+        );
+    }
+
+    let indent: String = ctx
+        .buffer
+        .lines()
+        .nth(line_num - 1)
+        .unwrap_or_default()
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+    // keep the statement's line breaks so later lines don't move
+    let separator = |i: usize| match stmt.breaks[i + 1].as_str() {
+        "" => " ",
+        b => b,
+    };
+    let args = strings_vec
+        .iter()
+        .enumerate()
+        .map(|(i, a)| match i {
+            0 => a.clone(),
+            i => format!(",{}{}", separator(i), a),
+        })
+        .collect();
+    let values = HashMap::from([
+        ("indent", indent),
+        ("call", stmt.call.clone()),
+        ("function", stmt.function.clone()),
+        ("severity", stmt.severity.clone()),
+        ("fcp", cpp_fcp_name(fcp)),
+        ("mdb", fcp.to_str()),
+        ("code", err.to_string()),
+        ("message", format!("{}{}", stmt.breaks[0], mdb_match)),
+        (
+            "comma_args",
+            strings_vec
+                .iter()
+                .enumerate()
+                .map(|(i, a)| format!(",{}{}", separator(i), a))
+                .collect(),
+        ),
+        (
+            "arg_calls",
+            strings_vec
+                .iter()
+                .enumerate()
+                .map(|(i, a)| format!("{}.arg({})", stmt.breaks[i + 1], a))
+                .collect(),
+        ),
+        ("args", args),
+    ]);
+    let template =
+        ctx.templates
+            .select(ctx.file_name, &fcp.to_str(), &stmt.function, &stmt.severity);
+    let replacement = render(template, &values).map_err(|e| Error::Template {
+        message: e.to_string(),
+    })?;
+    let replacement = Regex::new(r"[ \t]+(\r?\n)")
+        .unwrap()
+        .replace_all(&replacement, "$1");
+    let ending = if line.contains("\r\n") { "\r\n" } else { "\n" };
+    let missing = line
+        .matches('\n')
+        .count()
+        .saturating_sub(replacement.matches('\n').count());
+    let new_line = format!(
+        "{}{}{}{}",
+        stmt.prefix,
+        replacement,
+        ending.repeat(missing),
+        stmt.suffix
+    );
 
     let msg = format!(
         "------- Replacing log on line {} --------\n{}\n{}",
@@ -328,7 +660,7 @@ pub fn parse_line(
     );
 
     tx.send(AppEvent::Log(msg, LogLevel::Trace)).unwrap();
-    Some(new_line)
+    Ok(Some(new_line))
 }
 
 pub fn find_comment_around_line(file: &str, line_num: usize) -> String {
@@ -339,7 +671,7 @@ pub fn find_comment_around_line(file: &str, line_num: usize) -> String {
         curr_line += 1;
         if curr_line <= line_num {
             if line.trim().starts_with("//") {
-                if result.len() > 0 {
+                if !result.is_empty() {
                     result.push('\n');
                 }
                 result.push_str(line);
@@ -348,13 +680,11 @@ pub fn find_comment_around_line(file: &str, line_num: usize) -> String {
             } else if !result.is_empty() {
                 break;
             }
+        } else if line.trim().starts_with("//") {
+            result.push_str(line);
+            result.push('\n');
         } else {
-            if line.trim().starts_with("//") {
-                result.push_str(line);
-                result.push('\n');
-            } else {
-                break;
-            }
+            break;
         }
     }
 
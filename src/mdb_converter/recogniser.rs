@@ -0,0 +1,336 @@
+use serde::Deserialize;
+
+use super::lexer::{matching_close, split_top_level, tokenize, Token};
+use super::statement::{
+    find_statements, is_log_call, severity_call, stream_operands, LogStatement, Operands,
+    StatementSpan,
+};
+
+pub const SEVERITIES: [&str; 5] = ["debug", "info", "warning", "critical", "fatal"];
+
+/// One way log statements are written, like Qt streams or `printf` calls.
+pub trait Recogniser {
+    /// Name shown in the log.
+    fn name(&self) -> String;
+
+    /// Whether a statement of this style starts at `tokens[i]`.
+    fn starts_at(&self, tokens: &[Token], i: usize) -> bool;
+
+    /// Splits the first statement of this style in `src` into its parts.
+    /// `None` when its code isn't a string literal of a single word.
+    fn parse(&self, src: &str) -> Option<LogStatement>;
+}
+
+/// `qWarning() << "Code" << ...` and the other Qt logging calls.
+pub struct QtStream;
+
+impl Recogniser for QtStream {
+    fn name(&self) -> String {
+        "Qt stream".to_string()
+    }
+
+    fn starts_at(&self, tokens: &[Token], i: usize) -> bool {
+        is_log_call(tokens, i)
+    }
+
+    fn parse(&self, src: &str) -> Option<LogStatement> {
+        LogStatement::parse(src)
+    }
+}
+
+/// `sink << "Code" << ...;` for a stream like `std::cerr`.
+pub struct StreamSink {
+    pub sink: String,
+    pub severity: String,
+}
+
+impl Recogniser for StreamSink {
+    fn name(&self) -> String {
+        format!("{} stream", self.sink)
+    }
+
+    fn starts_at(&self, tokens: &[Token], i: usize) -> bool {
+        starts_with_sink(tokens, i, &self.sink, "<<").is_some()
+    }
+
+    fn parse(&self, src: &str) -> Option<LogStatement> {
+        let tokens = tokenize(src);
+        let (start, shift) = (0..tokens.len())
+            .find_map(|i| Some((i, starts_with_sink(&tokens, i, &self.sink, "<<")?)))?;
+        let after = tokens[shift - 1].end();
+        let (mut operands, end) = stream_operands(src, &tokens[shift + 1..], after)?;
+        // the rewritten statement ends the line itself
+        if operands
+            .args
+            .last()
+            .is_some_and(|a| a == "std::endl" || a == "endl")
+        {
+            operands.args.pop();
+            operands.breaks.pop();
+        }
+
+        Some(LogStatement {
+            prefix: src[..tokens[start].start].to_string(),
+            call: severity_call(&self.severity),
+            function: self.sink.clone(),
+            severity: self.severity.clone(),
+            code: operands.code,
+            args: operands.args,
+            breaks: operands.breaks,
+            suffix: src[end..].to_string(),
+        })
+    }
+}
+
+/// `sink("Code", ...);` for a function or macro like `printf` or `LOG_ERR`.
+pub struct CallSink {
+    pub sink: String,
+    pub severity: String,
+    /// Position of the code among the arguments, the following ones being
+    /// the message arguments.
+    pub code_arg: usize,
+}
+
+impl Recogniser for CallSink {
+    fn name(&self) -> String {
+        format!("{} call", self.sink)
+    }
+
+    fn starts_at(&self, tokens: &[Token], i: usize) -> bool {
+        starts_with_sink(tokens, i, &self.sink, "(").is_some()
+    }
+
+    fn parse(&self, src: &str) -> Option<LogStatement> {
+        let tokens = tokenize(src);
+        let (start, open) = (0..tokens.len())
+            .find_map(|i| Some((i, starts_with_sink(&tokens, i, &self.sink, "(")?)))?;
+        let close = matching_close(&tokens, open)?;
+        let end = tokens.get(close + 1).filter(|t| t.is(";"))?;
+
+        let groups = split_top_level(&tokens[open + 1..close], ",");
+        let after = match self.code_arg {
+            0 => tokens[open].end(),
+            n => groups.get(n - 1)?.last()?.end(),
+        };
+        let operands = Operands::read(src, groups.get(self.code_arg..)?, after)?;
+
+        Some(LogStatement {
+            prefix: src[..tokens[start].start].to_string(),
+            call: severity_call(&self.severity),
+            function: self.sink.clone(),
+            severity: self.severity.clone(),
+            code: operands.code,
+            args: operands.args,
+            breaks: operands.breaks,
+            suffix: src[end.end()..].to_string(),
+        })
+    }
+}
+
+/// Index of `next` when the tokens of `sink` followed by `next` start at
+/// `tokens[i]`, and `sink` isn't the tail of a longer name.
+fn starts_with_sink(tokens: &[Token], i: usize, sink: &str, next: &str) -> Option<usize> {
+    if i > 0 && ["::", ".", "->"].iter().any(|p| tokens[i - 1].is(p)) {
+        return None;
+    }
+    let sink_tokens = tokenize(sink);
+    let matches = (i..)
+        .zip(&sink_tokens)
+        .all(|(at, s)| tokens.get(at).is_some_and(|t| t.text == s.text));
+    let after = i + sink_tokens.len();
+    (matches && tokens.get(after)?.is(next)).then_some(after)
+}
+
+/// A recogniser as written in a recognisers file.
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum RecogniserConfig {
+    Stream {
+        sink: String,
+        severity: String,
+    },
+    Call {
+        sink: String,
+        severity: String,
+        #[serde(default)]
+        code_arg: usize,
+    },
+}
+
+/// Every recogniser a conversion uses, Qt streams first. Others come from a
+/// file like
+///
+/// ```json
+/// [
+///     { "kind": "stream", "sink": "std::cerr", "severity": "critical" },
+///     { "kind": "call", "sink": "printf", "severity": "info" },
+///     { "kind": "call", "sink": "LOG_ERR", "severity": "critical", "code_arg": 1 }
+/// ]
+/// ```
+pub struct Recognisers {
+    list: Vec<Box<dyn Recogniser>>,
+}
+
+impl Default for Recognisers {
+    fn default() -> Self {
+        Self {
+            list: vec![Box::new(QtStream)],
+        }
+    }
+}
+
+impl Recognisers {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let configs: Vec<RecogniserConfig> = serde_json::from_str(&contents)?;
+
+        let mut recognisers = Self::default();
+        for config in configs {
+            let (RecogniserConfig::Stream { sink, severity }
+            | RecogniserConfig::Call { sink, severity, .. }) = &config;
+            if tokenize(sink).is_empty() {
+                anyhow::bail!("empty sink in {}", path);
+            }
+            if !SEVERITIES.contains(&severity.as_str()) {
+                anyhow::bail!(
+                    "unknown severity {} for {}, expected one of {}",
+                    severity,
+                    sink,
+                    SEVERITIES.join(", ")
+                );
+            }
+
+            let recogniser: Box<dyn Recogniser> = match config {
+                RecogniserConfig::Stream { sink, severity } => {
+                    Box::new(StreamSink { sink, severity })
+                }
+                RecogniserConfig::Call {
+                    sink,
+                    severity,
+                    code_arg,
+                } => Box::new(CallSink {
+                    sink,
+                    severity,
+                    code_arg,
+                }),
+            };
+            recognisers.list.push(recogniser);
+        }
+        Ok(recognisers)
+    }
+
+    /// Every statement some recogniser matches in `src`, with that
+    /// recogniser. The first recogniser matching at a token wins.
+    pub fn find_statements(&self, src: &str) -> Vec<(&dyn Recogniser, StatementSpan)> {
+        find_statements(src, |tokens, i| {
+            self.list
+                .iter()
+                .find(|r| r.starts_at(tokens, i))
+                .map(|r| r.as_ref())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cerr() -> StreamSink {
+        StreamSink {
+            sink: "std::cerr".to_string(),
+            severity: "critical".to_string(),
+        }
+    }
+
+    fn log_err() -> CallSink {
+        CallSink {
+            sink: "LOG_ERR".to_string(),
+            severity: "critical".to_string(),
+            code_arg: 1,
+        }
+    }
+
+    #[test]
+    fn reads_stream_sinks_without_the_final_endl() {
+        let stmt = cerr()
+            .parse("  std::cerr << \"Err1\" << a\n            << std::endl; // x")
+            .unwrap();
+        assert_eq!(stmt.prefix, "  ");
+        assert_eq!(stmt.call, "qCritical()");
+        assert_eq!(stmt.function, "std::cerr");
+        assert_eq!(stmt.code, "Err1");
+        assert_eq!(stmt.args, ["a"]);
+        assert_eq!(stmt.breaks, ["", ""]);
+        assert_eq!(stmt.suffix, " // x");
+
+        let stmt = cerr().parse("std::cerr << \"Err1\" << endl << b;").unwrap();
+        assert_eq!(stmt.args, ["endl", "b"]);
+    }
+
+    #[test]
+    fn reads_the_code_argument_of_call_sinks() {
+        let stmt = log_err()
+            .parse("LOG_ERR(ctx, \"Err1\", a, f(b, c));")
+            .unwrap();
+        assert_eq!(stmt.call, "qCritical()");
+        assert_eq!(stmt.code, "Err1");
+        assert_eq!(stmt.args, ["a", "f(b, c)"]);
+
+        let printf = CallSink {
+            sink: "printf".to_string(),
+            severity: "info".to_string(),
+            code_arg: 0,
+        };
+        let stmt = printf.parse("printf(\"Err1\",\n       x);").unwrap();
+        assert_eq!(stmt.args, ["x"]);
+        assert_eq!(stmt.breaks, ["", "\n       "]);
+        assert!(log_err().parse("LOG_ERR(\"Err1\", a);").is_none());
+        assert!(log_err().parse("LOG_ERR(ctx, \"Err1\")").is_none());
+    }
+
+    #[test]
+    fn skips_sinks_that_are_part_of_a_longer_name() {
+        let printf = CallSink {
+            sink: "printf".to_string(),
+            severity: "info".to_string(),
+            code_arg: 0,
+        };
+        for src in [
+            "std::printf(\"Err1\");",
+            "obj.printf(\"Err1\");",
+            "obj->printf(\"Err1\");",
+            "my_printf(\"Err1\");",
+        ] {
+            let tokens = tokenize(src);
+            assert!(
+                !(0..tokens.len()).any(|i| printf.starts_at(&tokens, i)),
+                "{src}"
+            );
+        }
+        let tokens = tokenize("my::std::cerr << \"Err1\";");
+        assert!(!(0..tokens.len()).any(|i| cerr().starts_at(&tokens, i)));
+        let tokens = tokenize("std :: cerr << \"Err1\";");
+        assert!(cerr().starts_at(&tokens, 0));
+    }
+
+    #[test]
+    fn finds_statements_of_every_recogniser() {
+        let recognisers = Recognisers {
+            list: vec![Box::new(QtStream), Box::new(cerr()), Box::new(log_err())],
+        };
+        let src = "qInfo() << \"Err1\";\nstd::cerr << \"Err2\";\nLOG_ERR(c, \"Err3\");\n";
+        let found: Vec<(String, usize)> = recognisers
+            .find_statements(src)
+            .into_iter()
+            .map(|(r, span)| (r.name(), span.first_line))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("Qt stream".to_string(), 1),
+                ("std::cerr stream".to_string(), 2),
+                ("LOG_ERR call".to_string(), 3)
+            ]
+        );
+    }
+}
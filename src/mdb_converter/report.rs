@@ -0,0 +1,84 @@
+use std::fmt;
+
+/// Lines that need a human look after a conversion run.
+#[derive(Default, Debug)]
+pub struct Report {
+    pub unresolved: Vec<Issue>,
+    pub unknown: Vec<Issue>,
+    pub mismatches: Vec<Issue>,
+    pub unchecked: Vec<Issue>,
+    /// Lines an earlier run already converted.
+    pub converted: Vec<Issue>,
+}
+
+#[derive(Debug)]
+pub struct Issue {
+    pub file: String,
+    pub line: usize,
+    pub code: String,
+    pub reason: String,
+}
+
+impl Issue {
+    fn new(file: &str, line: usize, code: &str, reason: &str) -> Self {
+        Self {
+            file: file.to_string(),
+            line,
+            code: code.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl Report {
+    pub fn unresolved(&mut self, file: &str, line: usize, code: &str, reason: &str) {
+        self.unresolved.push(Issue::new(file, line, code, reason));
+    }
+
+    pub fn unknown(&mut self, file: &str, line: usize, code: &str, reason: &str) {
+        self.unknown.push(Issue::new(file, line, code, reason));
+    }
+
+    pub fn mismatch(&mut self, file: &str, line: usize, code: &str, reason: &str) {
+        self.mismatches.push(Issue::new(file, line, code, reason));
+    }
+
+    pub fn unchecked(&mut self, file: &str, line: usize, code: &str, reason: &str) {
+        self.unchecked.push(Issue::new(file, line, code, reason));
+    }
+
+    pub fn converted(&mut self, file: &str, line: usize, code: &str, reason: &str) {
+        self.converted.push(Issue::new(file, line, code, reason));
+    }
+
+    /// No line was left unconverted.
+    pub fn is_clean(&self) -> bool {
+        self.unresolved.is_empty() && self.unknown.is_empty() && self.mismatches.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.is_clean() && self.unchecked.is_empty() && self.converted.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let categories = [
+            ("unresolved", &self.unresolved),
+            ("unknown code", &self.unknown),
+            ("argument mismatch", &self.mismatches),
+            ("unchecked argument", &self.unchecked),
+            ("already converted", &self.converted),
+        ];
+        for (category, issues) in categories {
+            for i in issues {
+                writeln!(
+                    f,
+                    "{}:{}: {} {}: {}",
+                    i.file, i.line, category, i.code, i.reason
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
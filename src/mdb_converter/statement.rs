@@ -0,0 +1,352 @@
+use regex::Regex;
+
+use super::lexer::{matching_close, split_top_level, tokenize, Token, TokenKind};
+
+pub const LOG_FUNCTIONS: [&str; 9] = [
+    "qCritical",
+    "qDebug",
+    "qFatal",
+    "qInfo",
+    "qWarning",
+    "qCCritical",
+    "qCDebug",
+    "qCInfo",
+    "qCWarning",
+];
+
+/// Severity methods of `QMessageLogger(...)`.
+const LOGGER_METHODS: [&str; 5] = ["critical", "debug", "fatal", "info", "warning"];
+
+/// `QDebug` methods that can be chained before the first `<<`.
+const STREAM_MODIFIERS: [&str; 6] = [
+    "maybeQuote",
+    "maybeSpace",
+    "noquote",
+    "nospace",
+    "quote",
+    "space",
+];
+
+/// A Qt logging call such as `qCWarning(lc).noquote()`, up to the first
+/// `<<`.
+struct LogCall {
+    function: String,
+    severity: String,
+    /// Index of the last token of the call.
+    last: usize,
+}
+
+/// Recognises a logging call starting at `tokens[i]`.
+fn log_call(tokens: &[Token], i: usize) -> Option<LogCall> {
+    let first = tokens.get(i)?;
+    if first.kind != TokenKind::Ident || !tokens.get(i + 1)?.is("(") {
+        return None;
+    }
+    let is_method = |at: usize, names: &[&str]| {
+        tokens.get(at).is_some_and(|t| t.is("."))
+            && tokens
+                .get(at + 1)
+                .is_some_and(|t| t.kind == TokenKind::Ident && names.contains(&t.text))
+            && tokens.get(at + 2).is_some_and(|t| t.is("("))
+    };
+
+    let (severity, mut last) = if LOG_FUNCTIONS.contains(&first.text) {
+        let severity = first.text.trim_start_matches("qC").trim_start_matches('q');
+        (severity.to_lowercase(), matching_close(tokens, i + 1)?)
+    } else if first.text == "QMessageLogger" {
+        let close = matching_close(tokens, i + 1)?;
+        if !is_method(close + 1, &LOGGER_METHODS) {
+            return None;
+        }
+        let severity = tokens[close + 2].text.to_string();
+        (severity, matching_close(tokens, close + 3)?)
+    } else {
+        return None;
+    };
+
+    while is_method(last + 1, &STREAM_MODIFIERS) {
+        last = matching_close(tokens, last + 3)?;
+    }
+
+    Some(LogCall {
+        function: first.text.to_string(),
+        severity,
+        last,
+    })
+}
+
+/// A `qX() << "Code" << a << b;` statement split into its parts.
+#[derive(Clone, Debug)]
+pub struct LogStatement {
+    /// Everything before the log function, indentation included.
+    pub prefix: String,
+    /// Qt call the rewritten stream starts with: the original one, like
+    /// `qCWarning(lc).noquote()`, or the one for the severity when the
+    /// statement doesn't log through Qt.
+    pub call: String,
+    /// Log function or sink, like `qCWarning` or `std::cerr`.
+    pub function: String,
+    /// `debug`, `info`, `warning`, `critical` or `fatal`.
+    pub severity: String,
+    pub code: String,
+    pub args: Vec<String>,
+    /// Line break and indentation before the code and before each argument,
+    /// empty where the original statement stays on one line.
+    pub breaks: Vec<String>,
+    /// Whatever follows the closing `;`, usually a comment.
+    pub suffix: String,
+}
+
+impl LogStatement {
+    /// Finds the first log statement in `src` whose first streamed operand
+    /// is a string literal holding just an error code.
+    pub fn parse(src: &str) -> Option<Self> {
+        let tokens = tokenize(src);
+
+        let (func, call) = (0..tokens.len()).find_map(|i| Some((i, log_call(&tokens, i)?)))?;
+        if !tokens.get(call.last + 1)?.is("<<") {
+            return None;
+        }
+        let after = tokens[call.last].end();
+        let (operands, end) = stream_operands(src, &tokens[call.last + 2..], after)?;
+
+        Some(Self {
+            prefix: src[..tokens[func].start].to_string(),
+            call: src[tokens[func].start..tokens[call.last].end()].to_string(),
+            function: call.function,
+            severity: call.severity,
+            code: operands.code,
+            args: operands.args,
+            breaks: operands.breaks,
+            suffix: src[end..].to_string(),
+        })
+    }
+}
+
+/// The code and arguments of a log statement with the line breaks before
+/// each of them.
+pub struct Operands {
+    pub code: String,
+    pub args: Vec<String>,
+    pub breaks: Vec<String>,
+}
+
+impl Operands {
+    /// Reads `groups`, the code followed by the arguments, from `src`, where
+    /// the text preceding the code ends at `after`.
+    pub fn read(src: &str, groups: &[Vec<Token>], after: usize) -> Option<Self> {
+        let code = code_literal(groups.first()?)?;
+        let mut args = vec![];
+        let mut breaks = vec![];
+        let mut prev_end = after;
+        for (i, group) in groups.iter().enumerate() {
+            let (first, last) = (group.first()?, group.last()?);
+            breaks.push(line_break(src, prev_end, first.start));
+            if i > 0 {
+                args.push(operand_text(src, group)?);
+            }
+            prev_end = last.end();
+        }
+        Some(Self { code, args, breaks })
+    }
+}
+
+/// Line breaks between `from` and `to` followed by the indentation of the
+/// line `to` is on, or nothing when both are on the same line.
+fn line_break(src: &str, from: usize, to: usize) -> String {
+    let gap = &src[from..to];
+    let count = gap.matches('\n').count();
+    if count == 0 {
+        return String::new();
+    }
+    let ending = if gap.contains("\r\n") { "\r\n" } else { "\n" };
+    let line_start = src[..to].rfind('\n').map_or(0, |n| n + 1);
+    let indent: String = src[line_start..to]
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+    format!("{}{}", ending.repeat(count), indent)
+}
+
+/// Whether a Qt logging call starts at `tokens[i]`.
+pub fn is_log_call(tokens: &[Token], i: usize) -> bool {
+    log_call(tokens, i).is_some()
+}
+
+/// Qt call logging with `severity`, like `qWarning()`.
+pub fn severity_call(severity: &str) -> String {
+    let function = match severity {
+        "debug" => "qDebug",
+        "info" => "qInfo",
+        "warning" => "qWarning",
+        "fatal" => "qFatal",
+        _ => "qCritical",
+    };
+    format!("{}()", function)
+}
+
+/// Splits `"Code" << a << b;`, the tokens after the first `<<` of a stream,
+/// into its operands and the offset right after the `;`. The text before
+/// the stream ends at `after`.
+pub fn stream_operands(src: &str, rest: &[Token], after: usize) -> Option<(Operands, usize)> {
+    let end = end_of_statement(rest)?;
+    let groups = split_top_level(&rest[..end], "<<");
+    Some((Operands::read(src, &groups, after)?, rest[end].end()))
+}
+
+/// The code held by `operand` when it's a string literal of a single word.
+pub fn code_literal(operand: &[Token]) -> Option<String> {
+    let code = match operand {
+        [token] => token.str_contents()?.trim(),
+        _ => return None,
+    };
+    let code_re = Regex::new(r"^\w+$").unwrap();
+    code_re.is_match(code).then(|| code.to_string())
+}
+
+/// Source text of an operand, comments between its tokens included.
+pub fn operand_text(src: &str, operand: &[Token]) -> Option<String> {
+    let (first, last) = (operand.first()?, operand.last()?);
+    Some(src[first.start..last.end()].to_string())
+}
+
+/// Index of the `;` ending the statement, outside of any brackets.
+pub fn end_of_statement(tokens: &[Token]) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate() {
+        if token.opens() {
+            depth += 1;
+        } else if token.closes() {
+            depth = depth.checked_sub(1)?;
+        } else if depth == 0 && token.is(";") {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Byte range of a log statement in a file, from the log function to the
+/// closing `;`.
+#[derive(Copy, Clone, Debug)]
+pub struct StatementSpan {
+    pub start: usize,
+    pub end: usize,
+    pub first_line: usize,
+    pub last_line: usize,
+}
+
+/// Finds every statement in `src`, however many lines it spans, that
+/// `starts` recognises at one of its tokens. Returns what `starts` said
+/// about each of them.
+pub fn find_statements<T>(
+    src: &str,
+    starts: impl Fn(&[Token], usize) -> Option<T>,
+) -> Vec<(T, StatementSpan)> {
+    let tokens = tokenize(src);
+    let mut spans = vec![];
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = tokens[i];
+        let Some(found) = starts(&tokens, i) else {
+            i += 1;
+            continue;
+        };
+        let Some(end) = end_of_statement(&tokens[i..]) else {
+            i += 1;
+            continue;
+        };
+
+        let end_token = tokens[i + end];
+        let span = StatementSpan {
+            start: token.start,
+            end: end_token.end(),
+            first_line: line_of(src, token.start),
+            last_line: line_of(src, end_token.start),
+        };
+        spans.push((found, span));
+        i += end + 1;
+    }
+
+    spans
+}
+
+fn line_of(src: &str, offset: usize) -> usize {
+    src[..offset].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(src: &str) -> Vec<(usize, usize, &str)> {
+        find_statements(src, |tokens, i| is_log_call(tokens, i).then_some(()))
+            .into_iter()
+            .map(|(_, s)| (s.first_line, s.last_line, &src[s.start..s.end]))
+            .collect()
+    }
+
+    #[test]
+    fn finds_statements_spanning_lines() {
+        let src = "int a;\nqInfo() << \"Err1\"\n    << x; qWarning() << \"Err2\";\nf(\";\");\n";
+        assert_eq!(
+            spans(src),
+            [
+                (2, 3, "qInfo() << \"Err1\"\n    << x;"),
+                (3, 3, "qWarning() << \"Err2\";"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_semicolons_in_literals_comments_and_brackets() {
+        let src = "qWarning() << \"Err1\" /* ; */ << f(a; b) << \";\";";
+        assert_eq!(spans(src), [(1, 1, src)]);
+    }
+
+    #[test]
+    fn skips_calls_that_are_not_logging() {
+        assert!(spans("myqInfo() << \"Err1\"; obj.warning() << \"Err2\";").is_empty());
+    }
+
+    #[test]
+    fn parses_code_arguments_and_surroundings() {
+        let stmt =
+            LogStatement::parse("  qCWarning(lc).noquote() << \" Err1 \" << a << f(b, c); // x")
+                .unwrap();
+        assert_eq!(stmt.prefix, "  ");
+        assert_eq!(stmt.call, "qCWarning(lc).noquote()");
+        assert_eq!(stmt.function, "qCWarning");
+        assert_eq!(stmt.severity, "warning");
+        assert_eq!(stmt.code, "Err1");
+        assert_eq!(stmt.args, ["a", "f(b, c)"]);
+        assert_eq!(stmt.suffix, " // x");
+    }
+
+    #[test]
+    fn parses_message_logger_calls() {
+        let stmt =
+            LogStatement::parse("QMessageLogger(__FILE__, __LINE__, 0).critical() << \"Err1\";")
+                .unwrap();
+        assert_eq!(stmt.severity, "critical");
+        assert_eq!(
+            stmt.call,
+            "QMessageLogger(__FILE__, __LINE__, 0).critical()"
+        );
+        assert!(stmt.args.is_empty());
+    }
+
+    #[test]
+    fn keeps_line_breaks_before_operands() {
+        let stmt =
+            LogStatement::parse("qInfo()\r\n    << \"Err1\" << a\r\n           << b;").unwrap();
+        assert_eq!(stmt.breaks, ["\r\n    ", "", "\r\n           "]);
+    }
+
+    #[test]
+    fn needs_a_single_word_code_first() {
+        assert!(LogStatement::parse("qInfo() << \"two words\" << a;").is_none());
+        assert!(LogStatement::parse("qInfo() << name << a;").is_none());
+        assert!(LogStatement::parse("qInfo(\"Err1 %d\", 1);").is_none());
+    }
+}
@@ -0,0 +1,283 @@
+use globset::Glob;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+pub const DEFAULT_TEMPLATE: &str = "{call} << QString::asprintf({message}{comma_args}) << ENDL;";
+
+/// Names a template can use as `{name}`. `{{` and `}}` stand for braces.
+/// Replacements start at the log call, after the original indentation, so
+/// `{indent}` is only filled in right after a line break.
+pub const PLACEHOLDERS: [&str; 11] = [
+    "indent",
+    "call",
+    "function",
+    "severity",
+    "fcp",
+    "mdb",
+    "code",
+    "message",
+    "args",
+    "comma_args",
+    "arg_calls",
+];
+
+/// Replacement templates, from a file like
+///
+/// ```json
+/// {
+///     "default": "{call}.noquote() << QString::asprintf({message}{comma_args});",
+///     "rules": [
+///         { "fcp": "fcpasm", "template": "FCP_LOG({fcp}, {code}{comma_args});" },
+///         { "severity": "qCritical", "dir": "src/ui", "template": "..." }
+///     ]
+/// }
+/// ```
+///
+/// The first rule whose conditions all hold is used, the default otherwise.
+#[derive(Clone, Deserialize)]
+pub struct Templates {
+    #[serde(default = "default_template")]
+    pub default: String,
+    #[serde(default)]
+    pub rules: Vec<TemplateRule>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct TemplateRule {
+    /// Mdb file name, like `fcpasm`.
+    pub fcp: Option<String>,
+    /// Log function, like `qCWarning`, or severity, like `warning`.
+    pub severity: Option<String>,
+    /// Directory or glob the converted file has to be in.
+    pub dir: Option<String>,
+    pub template: String,
+}
+
+fn default_template() -> String {
+    DEFAULT_TEMPLATE.to_string()
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self {
+            default: default_template(),
+            rules: vec![],
+        }
+    }
+}
+
+impl Templates {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let templates: Self = serde_json::from_str(&contents)?;
+
+        let empty = HashMap::new();
+        for template in templates.rules.iter().map(|r| &r.template) {
+            render(template, &empty)?;
+        }
+        render(&templates.default, &empty)?;
+        for dir in templates.rules.iter().filter_map(|r| r.dir.as_ref()) {
+            Glob::new(dir)?;
+        }
+        Ok(templates)
+    }
+
+    /// Template for a statement calling `function` with `severity` in `file`,
+    /// converted with the mdb file `fcp`.
+    pub fn select(&self, file: &str, fcp: &str, function: &str, severity: &str) -> &str {
+        self.rules
+            .iter()
+            .find(|rule| {
+                rule.fcp.as_ref().is_none_or(|f| f == fcp)
+                    && rule
+                        .severity
+                        .as_ref()
+                        .is_none_or(|s| s == function || s == severity)
+                    && rule.dir.as_ref().is_none_or(|d| in_dir(file, d))
+            })
+            .map_or(&self.default, |rule| &rule.template)
+    }
+
+    /// Text every template writes right before `{message}`, back to the
+    /// previous whitespace or placeholder, like `QString::asprintf(` for the
+    /// default one.
+    pub fn message_markers(&self) -> Vec<String> {
+        // other placeholders differ from statement to statement
+        let mut values: HashMap<&str, String> = PLACEHOLDERS
+            .iter()
+            .map(|p| (*p, "\u{1}".to_string()))
+            .collect();
+        values.insert("message", "\0".to_string());
+        let mut markers = vec![];
+
+        let templates = self.rules.iter().map(|r| &r.template);
+        for template in templates.chain([&self.default]) {
+            let Ok(rendered) = render(template, &values) else {
+                continue;
+            };
+            let mut pieces: Vec<&str> = rendered.split('\0').collect();
+            pieces.pop();
+            for before in pieces {
+                let before = before.trim_end();
+                let marker = before
+                    .rsplit(|c: char| c.is_whitespace() || c == '\u{1}')
+                    .next()
+                    .unwrap_or("");
+                if !markers.iter().any(|m| m == marker) {
+                    markers.push(marker.to_string());
+                }
+            }
+        }
+        markers
+    }
+}
+
+fn in_dir(file: &str, dir: &str) -> bool {
+    let file = file.strip_prefix("./").unwrap_or(file);
+    let dir = dir.strip_prefix("./").unwrap_or(dir);
+    if std::path::Path::new(file).starts_with(dir) {
+        return true;
+    }
+    Glob::new(dir).is_ok_and(|g| g.compile_matcher().is_match(file))
+}
+
+/// Fills the placeholders of `template` with `values`. Names outside
+/// [`PLACEHOLDERS`] and unbalanced braces are errors.
+pub fn render(template: &str, values: &HashMap<&str, String>) -> anyhow::Result<String> {
+    let mut res = String::new();
+    let mut rest = template;
+
+    while let Some(i) = rest.find(['{', '}']) {
+        res.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            res.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        let close = match tail.strip_prefix('{').and_then(|t| t.find('}')) {
+            Some(close) => close + 1,
+            None => anyhow::bail!("unbalanced brace in template `{}`", template),
+        };
+        let name = &tail[1..close];
+        if !PLACEHOLDERS.contains(&name) {
+            anyhow::bail!(
+                "unknown placeholder {{{}}} in template `{}`",
+                name,
+                template
+            );
+        }
+        let after_break = res.ends_with('\n');
+        if let Some(value) = values.get(name).filter(|_| name != "indent" || after_break) {
+            res.push_str(value);
+        }
+        rest = &tail[close + 1..];
+    }
+
+    res.push_str(rest);
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&'static str, &str)]) -> HashMap<&'static str, String> {
+        pairs.iter().map(|(k, v)| (*k, v.to_string())).collect()
+    }
+
+    fn rule(
+        fcp: Option<&str>,
+        severity: Option<&str>,
+        dir: Option<&str>,
+        name: &str,
+    ) -> TemplateRule {
+        TemplateRule {
+            fcp: fcp.map(str::to_string),
+            severity: severity.map(str::to_string),
+            dir: dir.map(str::to_string),
+            template: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn fills_placeholders_and_escaped_braces() {
+        let values = values(&[("call", "qInfo()"), ("message", "\"m\"")]);
+        let rendered = render(
+            "{call} << [&]{{ return {message}; }}(){comma_args};",
+            &values,
+        );
+        assert_eq!(rendered.unwrap(), "qInfo() << [&]{ return \"m\"; }();");
+    }
+
+    #[test]
+    fn rejects_unknown_placeholders_and_unbalanced_braces() {
+        let empty = HashMap::new();
+        let e = render("{call} << {msg};", &empty).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "unknown placeholder {msg} in template `{call} << {msg};`"
+        );
+        assert!(render("{call", &empty).is_err());
+        assert!(render("call}", &empty).is_err());
+        assert!(render("{ call }", &empty).is_err());
+    }
+
+    #[test]
+    fn indents_only_after_line_breaks() {
+        let values = values(&[("indent", "    "), ("call", "qInfo()")]);
+        assert_eq!(
+            render("{indent}{call}\n{indent}<< x;", &values).unwrap(),
+            "qInfo()\n    << x;"
+        );
+        assert_eq!(render("a {indent}b", &values).unwrap(), "a b");
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let templates = Templates {
+            default: "default".to_string(),
+            rules: vec![
+                rule(Some("fcpasm"), Some("warning"), None, "asm warning"),
+                rule(None, Some("qCCritical"), Some("src/ui"), "ui critical"),
+                rule(Some("fcpasm"), None, None, "asm"),
+                rule(None, None, Some("**/legacy/*.cpp"), "legacy"),
+            ],
+        };
+        let select =
+            |file, fcp, function, severity| templates.select(file, fcp, function, severity);
+        assert_eq!(
+            select("a.cpp", "fcpasm", "qWarning", "warning"),
+            "asm warning"
+        );
+        assert_eq!(select("a.cpp", "fcpasm", "qInfo", "info"), "asm");
+        assert_eq!(
+            select("./src/ui/a.cpp", "fcpasm", "qCCritical", "critical"),
+            "ui critical"
+        );
+        assert_eq!(
+            select("src/ui/a.cpp", "fcpse", "qCritical", "critical"),
+            "default"
+        );
+        assert_eq!(
+            select("lib/legacy/a.cpp", "fcpse", "qInfo", "info"),
+            "legacy"
+        );
+        assert_eq!(select("src/a.cpp", "fcpse", "qInfo", "info"), "default");
+    }
+
+    #[test]
+    fn markers_precede_the_message() {
+        let templates = Templates {
+            default: DEFAULT_TEMPLATE.to_string(),
+            rules: vec![
+                rule(None, None, None, "{call} << tr({message}, {code});"),
+                rule(None, None, None, "FCP_LOG({fcp}, {message});"),
+            ],
+        };
+        assert_eq!(
+            templates.message_markers(),
+            ["tr(", ",", "QString::asprintf("]
+        );
+    }
+}
@@ -20,7 +20,10 @@ pub mod log_list;
 
 use log_list::*;
 
-use crate::mdb_converter::parser::*;
+use crate::mdb_converter::{
+    decisions::{DecisionFile, RecordingDecisions, TuiDecisions},
+    parser::*,
+};
 
 const HEADER_BG: Color = tailwind::BLUE.c950;
 const SELECTED_HEADER_BG: Color = tailwind::BLUE.c500;
@@ -64,6 +67,7 @@ pub struct InputField {
     active: bool,
 }
 
+#[allow(non_camel_case_types)]
 #[derive(Clone, Copy)]
 pub enum AppWidget {
     FILE_VIEWER,
@@ -97,6 +101,12 @@ pub struct App {
     ready_to_quit: bool,
 }
 
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl App {
     pub fn new() -> Self {
         Self {
@@ -133,7 +143,30 @@ impl App {
         let tx = app_sender.clone();
 
         thread::spawn(move || Self::handle_input(tx));
-        thread::spawn(move || parser(cli, app_sender.clone(), app2parser_receiver));
+        thread::spawn(move || {
+            let mut decisions = TuiDecisions::new(app_sender.clone(), app2parser_receiver);
+            let answers = cli
+                .answers
+                .clone()
+                .and_then(|path| match DecisionFile::load_or_default(&path) {
+                    Ok(file) => Some((path, file)),
+                    Err(e) => {
+                        let msg = format!("Couldn't load answers from {}: {}", path, e);
+                        app_sender
+                            .send(AppEvent::Log(msg, LogLevel::Error))
+                            .unwrap();
+                        None
+                    }
+                });
+            match answers {
+                Some((path, file)) => {
+                    let mut decisions =
+                        RecordingDecisions::new(decisions, &path, file, app_sender.clone());
+                    parser(cli, app_sender, &mut decisions)
+                }
+                None => parser(cli, app_sender, &mut decisions),
+            }
+        });
 
         for event in rx {
             match event {
@@ -156,17 +189,15 @@ impl App {
                 AppEvent::WidgetUp => self.current_widget.up(),
                 AppEvent::WidgetDown => self.current_widget.down(),
                 AppEvent::InputFielddBackspace => {
-                    if self.input_field.current_text.len() > 0 {
-                        let _ = self.input_field.current_text.pop();
-                    }
+                    let _ = self.input_field.current_text.pop();
                 }
-                AppEvent::InputFieldComplete => {
-                    if self.input_field.current_text.len() > 0 && self.input_field.active {
-                        app2parser_sender
-                            .send(AppEvent::Command(self.input_field.current_text.clone()))?;
-                        self.input_field.current_text.clear();
-                        self.input_field.active = false;
-                    }
+                AppEvent::InputFieldComplete
+                    if !self.input_field.current_text.is_empty() && self.input_field.active =>
+                {
+                    app2parser_sender
+                        .send(AppEvent::Command(self.input_field.current_text.clone()))?;
+                    self.input_field.current_text.clear();
+                    self.input_field.active = false;
                 }
                 AppEvent::Log(line, level) => match level {
                     LogLevel::Warn => warn!("{line}"),
@@ -215,20 +246,14 @@ impl App {
                         (KeyCode::Down | KeyCode::Char('j'), KeyModifiers::CONTROL) => {
                             tx_event.send(AppEvent::WidgetDown)?
                         }
-                        (KeyCode::Up, m) => {
-                            if m != KeyModifiers::CONTROL {
-                                tx_event.send(AppEvent::FileLineUp).unwrap();
-                            }
+                        (KeyCode::Up, m) if m != KeyModifiers::CONTROL => {
+                            tx_event.send(AppEvent::FileLineUp).unwrap();
                         }
-                        (KeyCode::Down, m) => {
-                            if m != KeyModifiers::CONTROL {
-                                tx_event.send(AppEvent::FileLineDown).unwrap();
-                            }
+                        (KeyCode::Down, m) if m != KeyModifiers::CONTROL => {
+                            tx_event.send(AppEvent::FileLineDown).unwrap();
                         }
-                        (KeyCode::Char(c), m) => {
-                            if m != KeyModifiers::CONTROL {
-                                tx_event.send(AppEvent::InputFieldChar(c))?
-                            }
+                        (KeyCode::Char(c), m) if m != KeyModifiers::CONTROL => {
+                            tx_event.send(AppEvent::InputFieldChar(c))?
                         }
                         (KeyCode::Enter, _) => tx_event
                             .send(AppEvent::InputFieldComplete)
@@ -373,7 +398,7 @@ impl FileViewer {
         self.select_line();
     }
 
-    pub fn to_item_vec(&self) -> Vec<ListItem> {
+    pub fn to_item_vec(&self) -> Vec<ListItem<'_>> {
         let res = self
             .contents
             .iter()
@@ -395,7 +420,7 @@ impl FileViewer {
             })
             .collect();
 
-        return res;
+        res
     }
 
     fn next(&mut self) {